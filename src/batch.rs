@@ -0,0 +1,338 @@
+use rayon::prelude::*;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{CodonAligner, Compression, Pn2CodonError, COMPRESSED_SUFFIXES};
+
+/// FASTA extensions stripped (after any compression suffix) to get a stem.
+const FASTA_SUFFIXES: &[&str] = &[".fa", ".fasta", ".faa", ".fna", ".fas", ".ffn"];
+
+/// One peptide/nucleotide file pair of a batch run.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BatchJob {
+    pub stem: String,
+    pub aa_path: PathBuf,
+    pub nt_path: PathBuf,
+}
+
+/// The gene stem of a FASTA file name: `gene1.fa.gz` becomes `gene1`.
+pub fn file_stem(path: &Path) -> String {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut stem = name.as_str();
+    for suffixes in [COMPRESSED_SUFFIXES, FASTA_SUFFIXES] {
+        let lower = stem.to_ascii_lowercase();
+        if let Some(suffix) = suffixes.iter().find(|suffix| lower.ends_with(*suffix)) {
+            stem = &stem[..stem.len() - suffix.len()];
+        }
+    }
+    stem.to_string()
+}
+
+/// The visible files of `dir` by stem, failing with
+/// [`Pn2CodonError::DuplicateStems`] when two of them share one.
+fn list_by_stem(dir: &Path) -> Result<BTreeMap<String, PathBuf>, Pn2CodonError> {
+    let io_error = |err: std::io::Error| Pn2CodonError::Io {
+        path: dir.display().to_string(),
+        message: err.to_string(),
+    };
+    let mut by_stem: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+    for entry in fs::read_dir(dir).map_err(io_error)? {
+        let path = entry.map_err(io_error)?.path();
+        let hidden = path
+            .file_name()
+            .is_none_or(|name| name.to_string_lossy().starts_with('.'));
+        if path.is_file() && !hidden {
+            by_stem.entry(file_stem(&path)).or_default().push(path);
+        }
+    }
+
+    let duplicates: Vec<(String, Vec<String>)> = by_stem
+        .iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .map(|(stem, paths)| {
+            let mut paths: Vec<String> = paths
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect();
+            paths.sort();
+            (stem.clone(), paths)
+        })
+        .collect();
+    if !duplicates.is_empty() {
+        return Err(Pn2CodonError::DuplicateStems {
+            dir: dir.display().to_string(),
+            duplicates,
+        });
+    }
+    Ok(by_stem
+        .into_iter()
+        .filter_map(|(stem, mut paths)| Some((stem, paths.pop()?)))
+        .collect())
+}
+
+/// Matches the files of `aa_dir` and `nt_dir` by stem.
+///
+/// Returns the matched jobs in stem order plus one
+/// [`Pn2CodonError::UnpairedFile`] per file without a partner.
+pub fn discover_jobs(
+    aa_dir: &Path,
+    nt_dir: &Path,
+) -> Result<(Vec<BatchJob>, Vec<Pn2CodonError>), Pn2CodonError> {
+    let aa_files = list_by_stem(aa_dir)?;
+    let mut nt_files = list_by_stem(nt_dir)?;
+
+    let mut jobs = Vec::new();
+    let mut unpaired = Vec::new();
+    for (stem, aa_path) in aa_files {
+        match nt_files.remove(&stem) {
+            Some(nt_path) => jobs.push(BatchJob {
+                stem,
+                aa_path,
+                nt_path,
+            }),
+            None => unpaired.push(Pn2CodonError::UnpairedFile {
+                stem,
+                path: aa_path.display().to_string(),
+                missing_in: nt_dir.display().to_string(),
+            }),
+        }
+    }
+    for (stem, nt_path) in nt_files {
+        unpaired.push(Pn2CodonError::UnpairedFile {
+            stem,
+            path: nt_path.display().to_string(),
+            missing_in: aa_dir.display().to_string(),
+        });
+    }
+    Ok((jobs, unpaired))
+}
+
+/// Reads a batch manifest: one `stem<TAB>aa_path<TAB>nt_path` line per job.
+///
+/// Relative paths are resolved against the manifest's directory. Blank lines
+/// and lines starting with `#` are skipped. Each stem names one output file
+/// in the output directory, so a stem used twice, or one that is not a
+/// plain file name, is an error.
+pub fn read_manifest(path: &Path) -> Result<Vec<BatchJob>, Pn2CodonError> {
+    let text = fs::read_to_string(path).map_err(|err| Pn2CodonError::Io {
+        path: path.display().to_string(),
+        message: err.to_string(),
+    })?;
+    let base = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut jobs = Vec::new();
+    let mut stem_lines: BTreeMap<String, usize> = BTreeMap::new();
+    for (line_index, line) in text.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split('\t').map(str::trim).collect();
+        match fields.as_slice() {
+            [stem, aa_path, nt_path] => {
+                if matches!(*stem, "" | "." | "..") || stem.contains(['/', '\\']) {
+                    return Err(Pn2CodonError::InvalidManifest {
+                        path: path.display().to_string(),
+                        line: line_index + 1,
+                        message: format!("stem '{}' is not a plain file name", stem),
+                    });
+                }
+                if let Some(first_line) = stem_lines.insert(stem.to_string(), line_index + 1) {
+                    return Err(Pn2CodonError::InvalidManifest {
+                        path: path.display().to_string(),
+                        line: line_index + 1,
+                        message: format!("stem '{}' is already used on line {}", stem, first_line),
+                    });
+                }
+                jobs.push(BatchJob {
+                    stem: stem.to_string(),
+                    aa_path: base.join(aa_path),
+                    nt_path: base.join(nt_path),
+                })
+            }
+            _ => {
+                return Err(Pn2CodonError::InvalidManifest {
+                    path: path.display().to_string(),
+                    line: line_index + 1,
+                    message: format!(
+                        "expected 3 tab-separated fields (stem, aa path, nt path), got {}",
+                        fields.len()
+                    ),
+                })
+            }
+        }
+    }
+    Ok(jobs)
+}
+
+/// How a batch run writes its outputs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BatchOptions {
+    /// Worker threads; `None` uses one per core.
+    pub threads: Option<usize>,
+    /// `Gzip` names outputs `<stem>.fa.gz`, otherwise `<stem>.fa`.
+    pub compression: Compression,
+}
+
+/// What happened to one stem of a batch run.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BatchOutcome {
+    pub stem: String,
+    /// The codon alignment written, or the error that stopped the stem.
+    pub result: Result<PathBuf, Pn2CodonError>,
+}
+
+/// Per-stem results of a batch run, in stem order for directory runs and
+/// manifest order otherwise.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BatchSummary {
+    pub outcomes: Vec<BatchOutcome>,
+}
+
+impl BatchSummary {
+    pub fn succeeded(&self) -> usize {
+        self.outcomes
+            .iter()
+            .filter(|outcome| outcome.result.is_ok())
+            .count()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.outcomes.len() - self.succeeded()
+    }
+
+    /// Renders the summary as TSV: `stem`, `status`, `output`, `error`.
+    pub fn to_tsv(&self) -> String {
+        let mut out = String::from("stem\tstatus\toutput\terror\n");
+        for outcome in &self.outcomes {
+            let (status, output, error) = match &outcome.result {
+                Ok(path) => ("ok", path.display().to_string(), ""),
+                Err(err) => ("failed", String::new(), err.title()),
+            };
+            out.push_str(&format!(
+                "{}\t{}\t{}\t{}\n",
+                outcome.stem, status, output, error
+            ));
+        }
+        out
+    }
+}
+
+impl CodonAligner {
+    /// Back-translates every job in parallel, writing one codon alignment
+    /// per stem into `out_dir`. A failing stem does not stop the others.
+    /// Only each stem's outcome is kept; per-record [`RecordMetadata`]
+    /// (masked codons, frameshifts and the like) is not.
+    ///
+    /// [`RecordMetadata`]: crate::RecordMetadata
+    pub fn align_batch(
+        &self,
+        jobs: &[BatchJob],
+        out_dir: &Path,
+        options: BatchOptions,
+    ) -> Result<BatchSummary, Pn2CodonError> {
+        fs::create_dir_all(out_dir).map_err(|err| Pn2CodonError::Write {
+            path: out_dir.display().to_string(),
+            message: err.to_string(),
+        })?;
+        let extension = match options.compression {
+            Compression::Gzip => "fa.gz",
+            Compression::Auto | Compression::Plain => "fa",
+        };
+
+        let run_job = |job: &BatchJob| {
+            let out_path = out_dir.join(format!("{}.{}", job.stem, extension));
+            let result = self
+                .align_files_to(&job.aa_path, &job.nt_path, &out_path, options.compression)
+                .map(|_| out_path);
+            BatchOutcome {
+                stem: job.stem.clone(),
+                result,
+            }
+        };
+
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(options.threads.unwrap_or(0))
+            .build()
+            .map_err(|err| Pn2CodonError::ThreadPool {
+                message: err.to_string(),
+            })?;
+        let outcomes = pool.install(|| jobs.par_iter().map(run_job).collect());
+        Ok(BatchSummary { outcomes })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory under the system temp dir for one test.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("pr2codon-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn duplicate_stems_in_a_directory_are_rejected() {
+        let dir = scratch_dir("duplicate-stems");
+        for name in ["gene1.fa", "gene1.fasta.gz", "gene2.fa"] {
+            fs::write(dir.join(name), ">a\nM\n").unwrap();
+        }
+        match discover_jobs(&dir, &dir) {
+            Err(Pn2CodonError::DuplicateStems { duplicates, .. }) => {
+                assert_eq!(duplicates.len(), 1);
+                assert_eq!(duplicates[0].0, "gene1");
+                assert_eq!(duplicates[0].1.len(), 2);
+            }
+            other => panic!("expected DuplicateStems, got {:?}", other),
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn duplicate_stems_in_a_manifest_are_rejected() {
+        let dir = scratch_dir("duplicate-manifest");
+        let manifest = dir.join("jobs.tsv");
+        fs::write(&manifest, "gene1\ta.fa\tn.fa\n# note\ngene1\tb.fa\tm.fa\n").unwrap();
+        match read_manifest(&manifest) {
+            Err(Pn2CodonError::InvalidManifest { line, message, .. }) => {
+                assert_eq!(line, 3);
+                assert!(message.contains("line 1"), "{}", message);
+            }
+            other => panic!("expected InvalidManifest, got {:?}", other),
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn manifest_stems_must_be_plain_file_names() {
+        let dir = scratch_dir("manifest-stems");
+        let manifest = dir.join("jobs.tsv");
+        for stem in ["../x", "/tmp/x", "a\\b", ".", ".."] {
+            fs::write(
+                &manifest,
+                format!("gene1\ta.fa\tn.fa\n{}\ta.fa\tn.fa\n", stem),
+            )
+            .unwrap();
+            match read_manifest(&manifest) {
+                Err(Pn2CodonError::InvalidManifest { line, .. }) => assert_eq!(line, 2, "{}", stem),
+                other => panic!("expected InvalidManifest for '{}', got {:?}", stem, other),
+            }
+        }
+        fs::write(&manifest, " \ta.fa\tn.fa\n").unwrap();
+        match read_manifest(&manifest) {
+            Err(Pn2CodonError::InvalidManifest { line, message, .. }) => {
+                assert_eq!(line, 1);
+                assert!(message.contains("plain file name"), "{}", message);
+            }
+            other => panic!("expected InvalidManifest, got {:?}", other),
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
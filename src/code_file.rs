@@ -0,0 +1,617 @@
+//! Custom genetic codes read from NCBI `gc.prt` or JSON files.
+//!
+//! A JSON file holds one code, in one of three shapes. With a signature,
+//! codons in `TCAG` order as in NCBI's `ncbieaa`/`sncbieaa` lines:
+//!
+//! ```json
+//! {
+//!   "id": 101,
+//!   "name": "My Mitochondrial",
+//!   "short_name": "MYMT",
+//!   "aliases": ["my_mito"],
+//!   "amino_acids": "FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+//!   "starts": "---M---------------M---------------M----------------------------"
+//! }
+//! ```
+//!
+//! With codon lists (`"*"` lists the stop codons):
+//!
+//! ```json
+//! { "name": "My Mitochondrial", "codons": { "F": ["TTT", "TTC"], ... }, "starts": ["ATG", "GTG"] }
+//! ```
+//!
+//! Or as a bare object of amino acid to codons, `{"F": ["TTT", "TTC"], ...}`,
+//! like the codon table of the Python entry points. Every field but
+//! `amino_acids`/`codons` is optional; `starts` may be a 64-character
+//! string or a list of codons, and without it the code has no alternative
+//! initiators.
+//!
+//! Whatever the shape, each of the 64 codons must be assigned exactly once.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::iter::Peekable;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::tables::{code_names, codon_at, codons_marked, normalize_name};
+use crate::{codon_index, CodonTable, GeneTable, GeneticCode, Pn2CodonError};
+
+/// A genetic code loaded at run time, laid out like a built-in
+/// [`GeneticCode`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CustomGeneticCode {
+    pub id: Option<i32>,
+    pub name: Option<String>,
+    pub short_name: Option<String>,
+    pub aliases: Vec<String>,
+    /// The amino acid of each codon, in `TCAG` order, `*` for stops.
+    pub amino_acids: String,
+    /// `M` at each codon that can start translation, `-` elsewhere.
+    pub starts: String,
+}
+
+impl CustomGeneticCode {
+    /// Builds a code from 64-character amino-acid and start strings,
+    /// checking both. NCBI's `*` marks in a start string are read as `-`.
+    pub(crate) fn from_signature(
+        amino_acids: &str,
+        starts: Option<&str>,
+    ) -> Result<CustomGeneticCode, String> {
+        let aa_count = amino_acids.chars().count();
+        if aa_count != 64 {
+            return Err(format!(
+                "amino-acid string has {} characters; expected 64, one per codon",
+                aa_count
+            ));
+        }
+        if let Some((index, aa)) = amino_acids
+            .chars()
+            .enumerate()
+            .find(|(_, aa)| !aa.is_ascii_uppercase() && *aa != '*')
+        {
+            return Err(format!(
+                "'{}' assigned to {} is not an amino acid symbol",
+                aa,
+                codon_at(index)
+            ));
+        }
+
+        let starts = match starts {
+            None => "-".repeat(64),
+            Some(starts) => {
+                let start_count = starts.chars().count();
+                if start_count != 64 {
+                    return Err(format!(
+                        "start string has {} characters; expected 64, one per codon",
+                        start_count
+                    ));
+                }
+                if let Some((index, mark)) = starts
+                    .chars()
+                    .enumerate()
+                    .find(|(_, mark)| !matches!(mark, 'M' | '-' | '*'))
+                {
+                    return Err(format!(
+                        "start mark '{}' for {} is not M, - or *",
+                        mark,
+                        codon_at(index)
+                    ));
+                }
+                starts.replace('*', "-")
+            }
+        };
+
+        Ok(CustomGeneticCode {
+            id: None,
+            name: None,
+            short_name: None,
+            aliases: Vec::new(),
+            amino_acids: amino_acids.to_string(),
+            starts,
+        })
+    }
+
+    /// Every name this code answers to in [`select_genetic_code`], in
+    /// snake_case.
+    pub fn names(&self) -> Vec<String> {
+        code_names(
+            self.name.as_deref(),
+            self.short_name.as_deref(),
+            self.aliases.iter().map(String::as_str),
+        )
+    }
+
+    /// The codons that can start translation, in `TCAG` order.
+    pub fn start_codons(&self) -> Vec<String> {
+        codons_marked(&self.starts, b'M')
+    }
+
+    /// The stop codons, in `TCAG` order.
+    pub fn stop_codons(&self) -> Vec<String> {
+        codons_marked(&self.amino_acids, b'*')
+    }
+
+    /// The code as an amino acid to codons mapping, stops under `*`.
+    pub fn gene_table(&self) -> GeneTable {
+        let mut gene_table = GeneTable::new();
+        for (index, aa) in self.amino_acids.chars().enumerate() {
+            gene_table.entry(aa).or_default().push(codon_at(index));
+        }
+        gene_table
+    }
+
+    pub fn codon_table(&self) -> CodonTable {
+        CodonTable::Signature {
+            amino_acids: Cow::Owned(self.amino_acids.clone()),
+            starts: Cow::Owned(self.starts.clone()),
+        }
+    }
+
+    /// `id: name` for listings, with whichever parts are known.
+    fn describe(&self) -> String {
+        match (self.id, &self.name) {
+            (Some(id), Some(name)) => format!("{}: {}", id, name),
+            (Some(id), None) => id.to_string(),
+            (None, Some(name)) => name.clone(),
+            (None, None) => "(unnamed)".to_string(),
+        }
+    }
+}
+
+impl From<&GeneticCode> for CustomGeneticCode {
+    fn from(code: &GeneticCode) -> Self {
+        CustomGeneticCode {
+            id: Some(code.id),
+            name: Some(code.name.to_string()),
+            short_name: code.short_name.map(str::to_string),
+            aliases: code.aliases.iter().map(|alias| alias.to_string()).collect(),
+            amino_acids: code.amino_acids.to_string(),
+            starts: code.starts.to_string(),
+        }
+    }
+}
+
+fn parse_codon(codon: &str) -> Result<usize, String> {
+    codon_index(codon.as_bytes()).ok_or_else(|| format!("'{}' is not a codon", codon))
+}
+
+/// The 64-character amino-acid string of amino acid to codon lists, which
+/// must assign every codon exactly once.
+fn assign_codons<'a>(
+    codons: impl IntoIterator<Item = (char, &'a [String])>,
+) -> Result<String, String> {
+    let mut assigned: [Option<char>; 64] = [None; 64];
+    for (aa, aa_codons) in codons {
+        if !aa.is_ascii_uppercase() && aa != '*' {
+            return Err(format!("'{}' is not an amino acid symbol", aa));
+        }
+        for codon in aa_codons {
+            let index = parse_codon(codon)?;
+            if let Some(previous) = assigned[index] {
+                return Err(format!(
+                    "codon {} is assigned to both '{}' and '{}'",
+                    codon_at(index),
+                    previous,
+                    aa
+                ));
+            }
+            assigned[index] = Some(aa);
+        }
+    }
+    let unassigned: Vec<String> = (0..64)
+        .filter(|index| assigned[*index].is_none())
+        .map(codon_at)
+        .collect();
+    if !unassigned.is_empty() {
+        return Err(format!(
+            "{} codon(s) are not assigned: {}",
+            unassigned.len(),
+            unassigned.join(", ")
+        ));
+    }
+    Ok(assigned.iter().flatten().collect())
+}
+
+/// A 64-character start string marking `codons`.
+fn start_string(codons: &[String]) -> Result<String, String> {
+    let mut marks = vec!['-'; 64];
+    for codon in codons {
+        marks[parse_codon(codon)?] = 'M';
+    }
+    Ok(marks.into_iter().collect())
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StartsJson {
+    Signature(String),
+    Codons(Vec<String>),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CodeJson {
+    id: Option<i32>,
+    name: Option<String>,
+    short_name: Option<String>,
+    #[serde(default)]
+    aliases: Vec<String>,
+    amino_acids: Option<String>,
+    codons: Option<BTreeMap<String, Vec<String>>>,
+    starts: Option<StartsJson>,
+}
+
+/// Turns `{"F": [...], ...}` keys into amino acid symbols.
+fn codon_lists(raw: &BTreeMap<String, Vec<String>>) -> Result<Vec<(char, &[String])>, String> {
+    raw.iter()
+        .map(|(key, codons)| {
+            let mut chars = key.chars();
+            match (chars.next(), chars.next()) {
+                (Some(aa), None) => Ok((aa, codons.as_slice())),
+                _ => Err(format!("key \"{}\" is not a single amino acid symbol", key)),
+            }
+        })
+        .collect()
+}
+
+/// Parses one genetic code from JSON; see the module documentation for the
+/// accepted shapes. `source` names the input in error messages.
+pub fn genetic_code_from_json(
+    json: &str,
+    source: &str,
+) -> Result<CustomGeneticCode, Pn2CodonError> {
+    let invalid = |message: String| Pn2CodonError::InvalidGeneTable {
+        source: source.to_string(),
+        message,
+    };
+    let value: serde_json::Value =
+        serde_json::from_str(json).map_err(|err| invalid(err.to_string()))?;
+    let described = value
+        .as_object()
+        .is_some_and(|object| object.contains_key("amino_acids") || object.contains_key("codons"));
+
+    if !described {
+        let raw: BTreeMap<String, Vec<String>> =
+            serde_json::from_value(value).map_err(|err| invalid(err.to_string()))?;
+        return codon_lists(&raw)
+            .and_then(assign_codons)
+            .and_then(|amino_acids| CustomGeneticCode::from_signature(&amino_acids, None))
+            .map_err(invalid);
+    }
+
+    let raw: CodeJson = serde_json::from_value(value).map_err(|err| invalid(err.to_string()))?;
+    let amino_acids = match (&raw.amino_acids, &raw.codons) {
+        (Some(amino_acids), None) => amino_acids.clone(),
+        (None, Some(codons)) => codon_lists(codons)
+            .and_then(assign_codons)
+            .map_err(invalid)?,
+        _ => {
+            return Err(invalid(
+                "give either \"amino_acids\" or \"codons\", not both".to_string(),
+            ))
+        }
+    };
+    let starts = match &raw.starts {
+        None => None,
+        Some(StartsJson::Signature(starts)) => Some(starts.clone()),
+        Some(StartsJson::Codons(codons)) => Some(start_string(codons).map_err(invalid)?),
+    };
+    let mut code =
+        CustomGeneticCode::from_signature(&amino_acids, starts.as_deref()).map_err(invalid)?;
+    code.id = raw.id;
+    code.name = raw.name;
+    code.short_name = raw.short_name;
+    code.aliases = raw.aliases;
+    Ok(code)
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Comma,
+    Word(String),
+    Text(String),
+}
+
+/// Splits `gc.prt` text into tokens tagged with their 1-based line,
+/// dropping `--` comments. Quoted strings may span lines; their line
+/// breaks and indentation collapse to single spaces.
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>, (usize, String)> {
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            c if c.is_whitespace() => {}
+            '-' if chars.peek() == Some(&'-') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        line += 1;
+                        break;
+                    }
+                }
+            }
+            '{' => tokens.push((Token::Open, line)),
+            '}' => tokens.push((Token::Close, line)),
+            ',' => tokens.push((Token::Comma, line)),
+            '"' => {
+                let start_line = line;
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        None => return Err((start_line, "unterminated string".to_string())),
+                        Some('"') if chars.peek() == Some(&'"') => {
+                            chars.next();
+                            value.push('"');
+                        }
+                        Some('"') => break,
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            value.push(c);
+                        }
+                    }
+                }
+                let value = value.split_whitespace().collect::<Vec<&str>>().join(" ");
+                tokens.push((Token::Text(value), start_line));
+            }
+            c => {
+                let mut word = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || matches!(next, '{' | '}' | ',' | '"') {
+                        break;
+                    }
+                    word.push(next);
+                    chars.next();
+                }
+                tokens.push((Token::Word(word), line));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn parse_gc_prt_entry<I>(
+    tokens: &mut Peekable<I>,
+    open_line: usize,
+) -> Result<CustomGeneticCode, (usize, String)>
+where
+    I: Iterator<Item = (Token, usize)>,
+{
+    let mut names = Vec::new();
+    let mut id = None;
+    let mut amino_acids = None;
+    let mut starts = None;
+    loop {
+        let (key, line) = match tokens.next() {
+            Some((Token::Word(key), line)) => (key, line),
+            Some((_, line)) => return Err((line, "expected a field name".to_string())),
+            None => return Err((open_line, "unterminated table".to_string())),
+        };
+        match (key.as_str(), tokens.next()) {
+            ("name", Some((Token::Text(name), _))) => names.push(name),
+            ("id", Some((Token::Word(value), line))) => {
+                id = Some(
+                    value
+                        .parse::<i32>()
+                        .map_err(|_| (line, format!("table id '{}' is not a number", value)))?,
+                )
+            }
+            ("ncbieaa", Some((Token::Text(value), _))) => amino_acids = Some(value),
+            ("sncbieaa", Some((Token::Text(value), _))) => starts = Some(value),
+            ("name" | "id" | "ncbieaa" | "sncbieaa", _) => {
+                return Err((line, format!("field '{}' has a malformed value", key)))
+            }
+            (other, _) => return Err((line, format!("unknown field '{}'", other))),
+        }
+        match tokens.next() {
+            Some((Token::Comma, _)) => continue,
+            Some((Token::Close, _)) => break,
+            Some((_, line)) => return Err((line, "expected ',' or '}'".to_string())),
+            None => return Err((open_line, "unterminated table".to_string())),
+        }
+    }
+
+    let amino_acids =
+        amino_acids.ok_or_else(|| (open_line, "table has no ncbieaa string".to_string()))?;
+    let mut code = CustomGeneticCode::from_signature(&amino_acids, starts.as_deref())
+        .map_err(|message| (open_line, message))?;
+    let mut names = names.into_iter();
+    code.id = id;
+    code.name = names.next();
+    code.short_name = names.next();
+    code.aliases = names.collect();
+    Ok(code)
+}
+
+/// Parses NCBI's `gc.prt` (the ASN.1 text behind `ftp.ncbi.nih.gov`'s
+/// genetic code list): `{ name "...", id N, ncbieaa "...", sncbieaa "..." }`
+/// blocks inside one outer `{ }`, with an optional `Genetic-code-table ::=`
+/// header. A second `name` becomes the short name and any further ones
+/// aliases. `source` names the input in error messages.
+pub fn parse_gc_prt(text: &str, source: &str) -> Result<Vec<CustomGeneticCode>, Pn2CodonError> {
+    let invalid = |(line, message): (usize, String)| Pn2CodonError::InvalidGeneTable {
+        source: source.to_string(),
+        message: format!("line {}: {}", line, message),
+    };
+    let mut tokens = tokenize(text).map_err(invalid)?.into_iter().peekable();
+    while matches!(tokens.peek(), Some((Token::Word(_), _))) {
+        tokens.next();
+    }
+    match tokens.next() {
+        Some((Token::Open, _)) => {}
+        Some((_, line)) => return Err(invalid((line, "expected '{'".to_string()))),
+        None => return Err(invalid((1, "no genetic code tables found".to_string()))),
+    }
+
+    let mut codes = Vec::new();
+    loop {
+        match tokens.next() {
+            Some((Token::Open, line)) => {
+                codes.push(parse_gc_prt_entry(&mut tokens, line).map_err(invalid)?)
+            }
+            Some((Token::Close, _)) if codes.is_empty() => break,
+            Some((_, line)) => return Err(invalid((line, "expected '{'".to_string()))),
+            None => return Err(invalid((1, "unterminated table list".to_string()))),
+        }
+        match tokens.next() {
+            Some((Token::Comma, _)) => continue,
+            Some((Token::Close, _)) => break,
+            Some((_, line)) => return Err(invalid((line, "expected ',' or '}'".to_string()))),
+            None => return Err(invalid((1, "unterminated table list".to_string()))),
+        }
+    }
+    if let Some((_, line)) = tokens.next() {
+        return Err(invalid((
+            line,
+            "unexpected text after the table list".to_string(),
+        )));
+    }
+    if codes.is_empty() {
+        return Err(invalid((1, "no genetic code tables found".to_string())));
+    }
+    Ok(codes)
+}
+
+/// Reads every genetic code in a file: JSON for `.json` files or text
+/// that parses as JSON, `gc.prt` otherwise.
+pub fn read_genetic_codes<P: AsRef<Path>>(
+    path: P,
+) -> Result<Vec<CustomGeneticCode>, Pn2CodonError> {
+    let path = path.as_ref();
+    let source = path.display().to_string();
+    let text = std::fs::read_to_string(path).map_err(|err| Pn2CodonError::Io {
+        path: source.clone(),
+        message: err.to_string(),
+    })?;
+    let is_json = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"))
+        || serde_json::from_str::<serde_json::Value>(&text).is_ok();
+    if is_json {
+        genetic_code_from_json(&text, &source).map(|code| vec![code])
+    } else {
+        parse_gc_prt(&text, &source)
+    }
+}
+
+/// Picks one code out of those read from `source`. `key` is an id or any
+/// of the code's names; without a key the file must hold exactly one code.
+pub fn select_genetic_code(
+    codes: Vec<CustomGeneticCode>,
+    key: Option<&str>,
+    source: &str,
+) -> Result<CustomGeneticCode, Pn2CodonError> {
+    let listing = || {
+        codes
+            .iter()
+            .map(CustomGeneticCode::describe)
+            .collect::<Vec<String>>()
+            .join("; ")
+    };
+    let found = match key {
+        None if codes.len() == 1 => codes.first(),
+        None => {
+            return Err(Pn2CodonError::InvalidGeneTable {
+                source: source.to_string(),
+                message: format!(
+                    "file defines {} genetic codes; choose one of {}",
+                    codes.len(),
+                    listing()
+                ),
+            })
+        }
+        Some(key) => {
+            let id = key.trim().parse::<i32>().ok();
+            let name = normalize_name(key);
+            codes
+                .iter()
+                .find(|code| (id.is_some() && code.id == id) || code.names().contains(&name))
+        }
+    };
+    match found {
+        Some(code) => Ok(code.clone()),
+        None => Err(Pn2CodonError::InvalidGeneTable {
+            source: source.to_string(),
+            message: format!(
+                "no genetic code '{}'; the file defines {}",
+                key.unwrap_or_default(),
+                listing()
+            ),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::genetic_code;
+
+    /// The head of NCBI's `gc.prt`, as distributed.
+    const GC_PRT: &str = r#"--**************************************************************************
+--  This is the NCBI genetic code table
+--  Initial base data set from Andrzej Elzanowski while at PIR International
+--  Addition of Eubacterial and Alternative Yeast by J.Ostell at NCBI
+--**************************************************************************
+Genetic-code-table ::= {
+ {
+  name "Standard" ,
+  name "SGC0" ,
+  id 1 ,
+  ncbieaa  "FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+  sncbieaa "---M------**--*----M---------------M----------------------------"
+  -- Base1  TTTTTTTTTTTTTTTTCCCCCCCCCCCCCCCCAAAAAAAAAAAAAAAAGGGGGGGGGGGGGGGG
+  -- Base2  TTTTCCCCAAAAGGGGTTTTCCCCAAAAGGGGTTTTCCCCAAAAGGGGTTTTCCCCAAAAGGGG
+  -- Base3  TCAGTCAGTCAGTCAGTCAGTCAGTCAGTCAGTCAGTCAGTCAGTCAGTCAGTCAGTCAGTCAG
+ },
+ {
+  name "Vertebrate Mitochondrial" ,
+  name "SGC1" ,
+  id 2 ,
+  ncbieaa  "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSS**VVVVAAAADDEEGGGG",
+  sncbieaa "----------**--------------------MMMM----------**---M------------"
+  -- Base1  TTTTTTTTTTTTTTTTCCCCCCCCCCCCCCCCAAAAAAAAAAAAAAAAGGGGGGGGGGGGGGGG
+  -- Base2  TTTTCCCCAAAAGGGGTTTTCCCCAAAAGGGGTTTTCCCCAAAAGGGGTTTTCCCCAAAAGGGG
+  -- Base3  TCAGTCAGTCAGTCAGTCAGTCAGTCAGTCAGTCAGTCAGTCAGTCAGTCAGTCAGTCAGTCAG
+ }
+}
+"#;
+
+    #[test]
+    fn parses_ncbi_gc_prt_entries() {
+        let codes = parse_gc_prt(GC_PRT, "gc.prt").unwrap();
+        assert_eq!(codes.len(), 2);
+
+        let mito = &codes[1];
+        assert_eq!(mito.id, Some(2));
+        assert_eq!(mito.name.as_deref(), Some("Vertebrate Mitochondrial"));
+        assert_eq!(mito.short_name.as_deref(), Some("SGC1"));
+        assert_eq!(mito.amino_acids, genetic_code(2).unwrap().amino_acids);
+        assert_eq!(mito.stop_codons(), ["TAA", "TAG", "AGA", "AGG"]);
+        assert_eq!(mito.start_codons(), ["ATT", "ATC", "ATA", "ATG", "GTG"]);
+
+        let standard = select_genetic_code(codes, Some("standard"), "gc.prt").unwrap();
+        assert_eq!(standard.id, Some(1));
+        assert_eq!(standard.starts, genetic_code(1).unwrap().starts);
+    }
+
+    #[test]
+    fn json_codes_must_assign_every_codon_once() {
+        let incomplete = genetic_code_from_json(r#"{"M": ["ATG"]}"#, "t.json");
+        assert!(matches!(
+            incomplete,
+            Err(Pn2CodonError::InvalidGeneTable { message, .. }) if message.contains("63 codon(s)")
+        ));
+
+        let json = r#"{"codons": {"M": ["ATG"], "I": ["ATG"]}}"#;
+        assert!(matches!(
+            genetic_code_from_json(json, "t.json"),
+            Err(Pn2CodonError::InvalidGeneTable { message, .. }) if message.contains("both")
+        ));
+    }
+}
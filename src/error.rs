@@ -0,0 +1,306 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::tables::describe_genetic_codes;
+use crate::{format_error_block, truncate_header};
+
+/// Identifies the record an error belongs to.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RecordContext {
+    /// 1-based position of the record in the input.
+    pub sequence_index: usize,
+    pub aa_source: String,
+    pub aa_header: String,
+    pub nt_source: String,
+    pub nt_header: String,
+}
+
+/// Why records could not be paired by ID.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PairingIssues {
+    pub aa_source: String,
+    pub nt_source: String,
+    pub duplicate_aa_ids: Vec<String>,
+    pub duplicate_nt_ids: Vec<String>,
+    pub only_in_aa: Vec<String>,
+    pub only_in_nt: Vec<String>,
+}
+
+/// Errors returned by the codon back-translation engine.
+///
+/// `Display` renders the same banner block the Python bindings have always
+/// raised, so the text is stable regardless of which API produced it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Pn2CodonError {
+    /// The requested NCBI table number is not a built-in genetic code.
+    InvalidTable { requested: i32 },
+    /// No built-in genetic code has the requested name.
+    UnknownTableName { requested: String },
+    /// A custom codon table could not be parsed.
+    InvalidGeneTable { source: String, message: String },
+    /// An input file could not be opened or read.
+    Io { path: String, message: String },
+    /// An output file could not be created or written.
+    Write { path: String, message: String },
+    /// An input file is not valid FASTA.
+    InvalidFasta {
+        path: String,
+        /// 1-based line number.
+        line: usize,
+        message: String,
+    },
+    /// The peptide and nucleotide files hold different numbers of records.
+    RecordCountMismatch {
+        aa_source: String,
+        aa_count: usize,
+        nt_source: String,
+        nt_count: usize,
+    },
+    /// An ID-extraction regex does not compile.
+    InvalidIdPattern { pattern: String, message: String },
+    /// Records could not be matched up by ID across the two inputs.
+    Pairing(Box<PairingIssues>),
+    /// A batch input file has no same-stem partner in the other directory.
+    UnpairedFile {
+        stem: String,
+        path: String,
+        missing_in: String,
+    },
+    /// Several files of a batch directory share a stem, so they cannot be
+    /// matched up unambiguously.
+    DuplicateStems {
+        dir: String,
+        /// Each shared stem with the files that have it.
+        duplicates: Vec<(String, Vec<String>)>,
+    },
+    /// A batch manifest line is malformed.
+    InvalidManifest {
+        path: String,
+        /// 1-based line number.
+        line: usize,
+        message: String,
+    },
+    /// The batch worker pool could not be started.
+    ThreadPool { message: String },
+    /// The peptide and nucleotide headers of a record differ.
+    HeaderMismatch { record: Box<RecordContext> },
+    /// The nucleotide length is not three times the residue count.
+    LengthMismatch {
+        record: Box<RecordContext>,
+        residues: usize,
+        nt_len: usize,
+        details: String,
+    },
+    /// A residue has no entry in the custom codon table.
+    MissingAminoAcid {
+        record: Box<RecordContext>,
+        amino_acid: char,
+        /// 1-based column in the aligned peptide.
+        alignment_position: usize,
+        valid_symbols: Vec<char>,
+    },
+    /// A codon does not encode its aligned residue, or the nucleotide
+    /// sequence ran out before the peptide did.
+    CodonMismatch {
+        record: Box<RecordContext>,
+        /// 1-based column in the aligned peptide.
+        alignment_position: usize,
+        /// 0-based offset of the codon in the cleaned nucleotide sequence.
+        nt_offset: usize,
+        expected_aa: char,
+        /// The observed codon; shorter than three bases when the sequence
+        /// ended early.
+        codon: String,
+        details: String,
+    },
+}
+
+impl Pn2CodonError {
+    /// The record this error refers to, if it is record-specific.
+    pub fn record(&self) -> Option<&RecordContext> {
+        match self {
+            Pn2CodonError::InvalidTable { .. }
+            | Pn2CodonError::UnknownTableName { .. }
+            | Pn2CodonError::InvalidGeneTable { .. }
+            | Pn2CodonError::Io { .. }
+            | Pn2CodonError::Write { .. }
+            | Pn2CodonError::InvalidFasta { .. }
+            | Pn2CodonError::RecordCountMismatch { .. }
+            | Pn2CodonError::InvalidIdPattern { .. }
+            | Pn2CodonError::Pairing(_)
+            | Pn2CodonError::UnpairedFile { .. }
+            | Pn2CodonError::DuplicateStems { .. }
+            | Pn2CodonError::InvalidManifest { .. }
+            | Pn2CodonError::ThreadPool { .. } => None,
+            Pn2CodonError::HeaderMismatch { record }
+            | Pn2CodonError::LengthMismatch { record, .. }
+            | Pn2CodonError::MissingAminoAcid { record, .. }
+            | Pn2CodonError::CodonMismatch { record, .. } => Some(record.as_ref()),
+        }
+    }
+
+    pub fn sequence_index(&self) -> Option<usize> {
+        self.record().map(|record| record.sequence_index)
+    }
+
+    /// The banner title line.
+    pub fn title(&self) -> &'static str {
+        match self {
+            Pn2CodonError::InvalidTable { .. } => "Invalid codon table number.",
+            Pn2CodonError::UnknownTableName { .. } => "Unknown codon table name.",
+            Pn2CodonError::InvalidGeneTable { .. } => "Invalid custom codon table.",
+            Pn2CodonError::Io { .. } => "Could not read input file.",
+            Pn2CodonError::Write { .. } => "Could not write output file.",
+            Pn2CodonError::InvalidFasta { .. } => "Malformed FASTA input.",
+            Pn2CodonError::RecordCountMismatch { .. } => {
+                "Peptide and nucleotide files have different record counts."
+            }
+            Pn2CodonError::InvalidIdPattern { .. } => "Invalid record ID pattern.",
+            Pn2CodonError::Pairing(_) => "Peptide and nucleotide records could not be paired.",
+            Pn2CodonError::UnpairedFile { .. } => "Input file has no partner with the same stem.",
+            Pn2CodonError::DuplicateStems { .. } => "Several input files share a stem.",
+            Pn2CodonError::InvalidManifest { .. } => "Malformed batch manifest.",
+            Pn2CodonError::ThreadPool { .. } => "Could not start worker threads.",
+            Pn2CodonError::HeaderMismatch { .. } => {
+                "Header mismatch between peptide and nucleotide records."
+            }
+            Pn2CodonError::LengthMismatch { .. } => {
+                "Peptide and nucleotide lengths are inconsistent."
+            }
+            Pn2CodonError::MissingAminoAcid { .. } => {
+                "Amino acid is missing from custom codon table."
+            }
+            Pn2CodonError::CodonMismatch { .. } => {
+                "Peptide and nucleotide sequences are inconsistent."
+            }
+        }
+    }
+
+    /// The body of the banner block, without the surrounding rules.
+    pub fn details(&self) -> String {
+        match self {
+            Pn2CodonError::InvalidTable { requested } => format!(
+                "Requested table : {}\nSupported NCBI tables:\n{}",
+                requested,
+                describe_genetic_codes()
+            ),
+            Pn2CodonError::UnknownTableName { requested } => format!(
+                "Requested table : {}\nSupported NCBI tables:\n{}",
+                requested,
+                describe_genetic_codes()
+            ),
+            Pn2CodonError::InvalidGeneTable { source, message } => {
+                format!("Source : {}\nReason : {}", source, message)
+            }
+            Pn2CodonError::Io { path, message } | Pn2CodonError::Write { path, message } => {
+                format!("File   : {}\nReason : {}", path, message)
+            }
+            Pn2CodonError::InvalidFasta {
+                path,
+                line,
+                message,
+            }
+            | Pn2CodonError::InvalidManifest {
+                path,
+                line,
+                message,
+            } => format!("File   : {}\nLine   : {}\nReason : {}", path, line, message),
+            Pn2CodonError::RecordCountMismatch {
+                aa_source,
+                aa_count,
+                nt_source,
+                nt_count,
+            } => format!(
+                "Peptide records ({})    : {}\nNucleotide records ({}) : {}",
+                aa_source, aa_count, nt_source, nt_count
+            ),
+            Pn2CodonError::InvalidIdPattern { pattern, message } => {
+                format!("Pattern : {}\nReason  : {}", pattern, message)
+            }
+            Pn2CodonError::UnpairedFile {
+                stem,
+                path,
+                missing_in,
+            } => format!(
+                "Stem       : {}\nFile       : {}\nMissing in : {}",
+                stem, path, missing_in
+            ),
+            Pn2CodonError::DuplicateStems { dir, duplicates } => {
+                let mut out = format!("Directory : {}\n", dir);
+                for (stem, paths) in duplicates {
+                    out.push_str(&format!("Stem {} : {}\n", stem, paths.join(", ")));
+                }
+                out
+            }
+            Pn2CodonError::ThreadPool { message } => format!("Reason : {}", message),
+            Pn2CodonError::Pairing(issues) => {
+                let mut out = String::new();
+                for (label, ids) in [
+                    (
+                        format!("Duplicate IDs ({})", issues.aa_source),
+                        &issues.duplicate_aa_ids,
+                    ),
+                    (
+                        format!("Duplicate IDs ({})", issues.nt_source),
+                        &issues.duplicate_nt_ids,
+                    ),
+                    (format!("Only in {}", issues.aa_source), &issues.only_in_aa),
+                    (format!("Only in {}", issues.nt_source), &issues.only_in_nt),
+                ] {
+                    if !ids.is_empty() {
+                        out.push_str(&format!("{} : {}\n", label, summarize_ids(ids)));
+                    }
+                }
+                out
+            }
+            Pn2CodonError::HeaderMismatch { record } => format!(
+                "Sequence index: {}\nExpected header ({}): \"{}\"\nheader found ({}): \"{}\"",
+                record.sequence_index,
+                record.aa_source,
+                truncate_header(&record.aa_header),
+                record.nt_source,
+                truncate_header(&record.nt_header)
+            ),
+            Pn2CodonError::MissingAminoAcid {
+                amino_acid,
+                alignment_position,
+                valid_symbols,
+                ..
+            } => format!(
+                "Amino acid         : '{}'\nAlignment position : {}\nValid symbols      : {}",
+                amino_acid,
+                alignment_position,
+                valid_symbols
+                    .iter()
+                    .map(char::to_string)
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Pn2CodonError::LengthMismatch { details, .. }
+            | Pn2CodonError::CodonMismatch { details, .. } => details.clone(),
+        }
+    }
+}
+
+/// Lists at most 20 IDs, noting how many were left out.
+fn summarize_ids(ids: &[String]) -> String {
+    const LIMIT: usize = 20;
+    let mut out = ids
+        .iter()
+        .take(LIMIT)
+        .map(String::as_str)
+        .collect::<Vec<&str>>()
+        .join(", ");
+    if ids.len() > LIMIT {
+        out.push_str(&format!(" ... ({} more)", ids.len() - LIMIT));
+    }
+    out
+}
+
+impl fmt::Display for Pn2CodonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&format_error_block(self.title(), &self.details()))
+    }
+}
+
+impl Error for Pn2CodonError {}
@@ -0,0 +1,282 @@
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+use crate::{source_label_from_path, PairedRecord, Pn2CodonError, COMPRESSED_SUFFIXES};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// One FASTA record. `header` excludes the leading `>`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FastaRecord {
+    pub header: String,
+    pub sequence: String,
+}
+
+/// Opens a file for line reading, transparently decompressing gzip and
+/// bgzip input. Compression is detected from the content, not the name.
+pub fn open_input<P: AsRef<Path>>(path: P) -> Result<Box<dyn BufRead>, Pn2CodonError> {
+    let path = path.as_ref();
+    let io_error = |err: std::io::Error| Pn2CodonError::Io {
+        path: path.display().to_string(),
+        message: err.to_string(),
+    };
+    let mut reader = BufReader::new(File::open(path).map_err(io_error)?);
+    let is_gzip = reader
+        .fill_buf()
+        .map_err(io_error)?
+        .starts_with(&GZIP_MAGIC);
+    if is_gzip {
+        // bgzip files are a series of gzip members, so a multi-member
+        // decoder reads both.
+        Ok(Box::new(BufReader::new(MultiGzDecoder::new(reader))))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+/// Reads every record of a FASTA file, which may be gzip or bgzip
+/// compressed.
+pub fn read_fasta<P: AsRef<Path>>(path: P) -> Result<Vec<FastaRecord>, Pn2CodonError> {
+    let path = path.as_ref();
+    parse_fasta(open_input(path)?, &path.display().to_string())
+}
+
+/// Whether [`write_output`] compresses what it writes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Compression {
+    /// Gzip when the output path ends in `.gz`, `.bgz` or `.bgzf`.
+    #[default]
+    Auto,
+    Plain,
+    Gzip,
+}
+
+impl Compression {
+    fn applies_to(self, path: &Path) -> bool {
+        match self {
+            Compression::Auto => {
+                let name = path.to_string_lossy().to_ascii_lowercase();
+                COMPRESSED_SUFFIXES
+                    .iter()
+                    .any(|suffix| name.ends_with(suffix))
+            }
+            Compression::Plain => false,
+            Compression::Gzip => true,
+        }
+    }
+}
+
+/// Writes `contents` to `path`, gzip-compressed according to `compression`.
+pub fn write_output<P: AsRef<Path>>(
+    path: P,
+    contents: &str,
+    compression: Compression,
+) -> Result<(), Pn2CodonError> {
+    let path = path.as_ref();
+    let write_error = |err: std::io::Error| Pn2CodonError::Write {
+        path: path.display().to_string(),
+        message: err.to_string(),
+    };
+    let file = File::create(path).map_err(write_error)?;
+    if compression.applies_to(path) {
+        let mut encoder = GzEncoder::new(file, flate2::Compression::default());
+        encoder
+            .write_all(contents.as_bytes())
+            .map_err(write_error)?;
+        encoder.finish().map_err(write_error)?;
+    } else {
+        let mut file = file;
+        file.write_all(contents.as_bytes()).map_err(write_error)?;
+    }
+    Ok(())
+}
+
+/// Parses FASTA text from `reader`.
+///
+/// Sequences may span several lines; Windows line endings, blank lines and
+/// whitespace inside sequence lines are ignored. `source` names the input in
+/// error messages.
+pub fn parse_fasta<R: BufRead>(reader: R, source: &str) -> Result<Vec<FastaRecord>, Pn2CodonError> {
+    let mut records = Vec::new();
+    let mut current: Option<FastaRecord> = None;
+
+    for (line_index, line) in reader.lines().enumerate() {
+        let line = line.map_err(|err| Pn2CodonError::Io {
+            path: source.to_string(),
+            message: err.to_string(),
+        })?;
+        let line = line.trim_end_matches(['\r', '\n']);
+
+        if let Some(header) = line.strip_prefix('>') {
+            if let Some(record) = current.take() {
+                records.push(record);
+            }
+            current = Some(FastaRecord {
+                header: header.trim().to_string(),
+                sequence: String::new(),
+            });
+            continue;
+        }
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match current.as_mut() {
+            Some(record) => record
+                .sequence
+                .extend(line.chars().filter(|c| !c.is_whitespace())),
+            None => {
+                return Err(Pn2CodonError::InvalidFasta {
+                    path: source.to_string(),
+                    line: line_index + 1,
+                    message: "sequence data found before the first '>' header".to_string(),
+                })
+            }
+        }
+    }
+
+    if let Some(record) = current.take() {
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Reads a peptide and a nucleotide FASTA file and pairs their records by
+/// position. Each pair's output header is the peptide header.
+pub fn read_paired_records<P, Q>(aa_path: P, nt_path: Q) -> Result<Vec<PairedRecord>, Pn2CodonError>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let aa_records = read_fasta(&aa_path)?;
+    let nt_records = read_fasta(&nt_path)?;
+
+    if aa_records.len() != nt_records.len() {
+        return Err(Pn2CodonError::RecordCountMismatch {
+            aa_source: source_label_from_path(&aa_path.as_ref().to_string_lossy(), "aa.fa"),
+            aa_count: aa_records.len(),
+            nt_source: source_label_from_path(&nt_path.as_ref().to_string_lossy(), "nt.fa"),
+            nt_count: nt_records.len(),
+        });
+    }
+
+    Ok(aa_records
+        .into_iter()
+        .zip(nt_records)
+        .map(|(aa, nt)| PairedRecord {
+            header: format!(">{}", aa.header),
+            aa_header: aa.header,
+            aa: aa.sequence,
+            nt_header: nt.header,
+            nt: nt.sequence,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// A fresh path under the system temp dir for one test.
+    fn scratch_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("pr2codon-{}-{}", std::process::id(), name))
+    }
+
+    fn gzip(text: &str) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(text.as_bytes()).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn record(header: &str, sequence: &str) -> FastaRecord {
+        FastaRecord {
+            header: header.to_string(),
+            sequence: sequence.to_string(),
+        }
+    }
+
+    #[test]
+    fn reads_plain_and_gzip_input() {
+        let text = ">a one\nATG\nAAA\n>b\nTTT\n";
+        let expected = vec![record("a one", "ATGAAA"), record("b", "TTT")];
+
+        // The name says nothing about the content.
+        let plain = scratch_path("plain.fa.gz");
+        std::fs::write(&plain, text).unwrap();
+        assert_eq!(read_fasta(&plain).unwrap(), expected);
+
+        let gzipped = scratch_path("gzipped.fa");
+        std::fs::write(&gzipped, gzip(text)).unwrap();
+        assert_eq!(read_fasta(&gzipped).unwrap(), expected);
+
+        std::fs::remove_file(plain).unwrap();
+        std::fs::remove_file(gzipped).unwrap();
+    }
+
+    #[test]
+    fn reads_every_member_of_multi_member_gzip() {
+        let mut bytes = gzip(">a\nATG\n");
+        bytes.extend(gzip("AAA\n>b\n"));
+        bytes.extend(gzip("TTT"));
+        let path = scratch_path("members.fa.bgz");
+        std::fs::write(&path, bytes).unwrap();
+        assert_eq!(
+            read_fasta(&path).unwrap(),
+            vec![record("a", "ATGAAA"), record("b", "TTT")]
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn tolerates_crlf_blank_lines_and_no_final_newline() {
+        let text = ">a\r\nAT G\r\n\r\nAAA\r\n\n>b \r\nTTT";
+        assert_eq!(
+            parse_fasta(text.as_bytes(), "x.fa").unwrap(),
+            vec![record("a", "ATGAAA"), record("b", "TTT")]
+        );
+        assert!(matches!(
+            parse_fasta("\nATG\n>a\n".as_bytes(), "x.fa"),
+            Err(Pn2CodonError::InvalidFasta { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn auto_compression_follows_the_output_name() {
+        let text = ">a\nATGAAA\n";
+        let gzipped = scratch_path("out.fa.gz");
+        write_output(&gzipped, text, Compression::Auto).unwrap();
+        assert!(std::fs::read(&gzipped).unwrap().starts_with(&GZIP_MAGIC));
+        assert_eq!(read_fasta(&gzipped).unwrap(), vec![record("a", "ATGAAA")]);
+
+        let plain = scratch_path("out.fa");
+        write_output(&plain, text, Compression::Auto).unwrap();
+        assert_eq!(std::fs::read_to_string(&plain).unwrap(), text);
+
+        write_output(&plain, text, Compression::Gzip).unwrap();
+        assert!(std::fs::read(&plain).unwrap().starts_with(&GZIP_MAGIC));
+        write_output(&gzipped, text, Compression::Plain).unwrap();
+        assert_eq!(std::fs::read_to_string(&gzipped).unwrap(), text);
+
+        std::fs::remove_file(gzipped).unwrap();
+        std::fs::remove_file(plain).unwrap();
+    }
+
+    #[test]
+    fn source_labels_keep_compression_suffixes() {
+        assert_eq!(
+            source_label_from_path("dir/gene1.fa.gz", "aa.fa"),
+            "gene1.fa.gz"
+        );
+        assert_eq!(source_label_from_path("gene1.BGZ", "aa.fa"), "gene1.fa.BGZ");
+        assert_eq!(
+            source_label_from_path("reads/gene1.gz", "nt.fa"),
+            "gene1.fa.gz"
+        );
+        assert_eq!(source_label_from_path("  ", "nt.fa"), "nt.fa");
+    }
+}
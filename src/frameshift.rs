@@ -0,0 +1,369 @@
+//! Frameshift-tolerant alignment of a peptide to its nucleotide record.
+//!
+//! Each residue normally takes three bases. A frameshift lets it take one
+//! or two, when bases are missing from the record, or four or five, when
+//! the record has extra bases. Missing bases are written as `!`, like
+//! MACSE does, and extra bases are left out so every residue still gets one
+//! codon column. A banded dynamic program places the shifts at the lowest
+//! cost: 1 for each codon that does not encode its residue plus the
+//! penalty of each shift.
+
+use std::fmt;
+
+/// Most dynamic-programming cells one record may use (about 40 MB); records
+/// that drift further from three bases per residue are left as they are.
+/// With fewer than 2^22 residues and penalties below 2^32, no cost comes
+/// near overflowing a `u64`.
+const MAX_CELLS: usize = 1 << 22;
+
+/// Cost of a cell no alignment reaches.
+const UNREACHED: u64 = u64::MAX;
+
+/// Settings of the frameshift-tolerant alignment.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrameshiftAlignment {
+    /// Cost of one frameshift, against 1 for each codon that does not
+    /// encode its residue. A penalty of 0 counts as 1, so shifts are never
+    /// free.
+    pub penalty: u32,
+    /// How many bases the alignment may drift from three per residue,
+    /// beyond the length difference of the records.
+    pub band: usize,
+}
+
+impl Default for FrameshiftAlignment {
+    fn default() -> Self {
+        Self {
+            penalty: 5,
+            band: 12,
+        }
+    }
+}
+
+/// A frameshift placed by the frameshift-tolerant alignment.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frameshift {
+    /// 1-based column in the aligned peptide of the residue whose codon
+    /// holds the shift.
+    pub alignment_position: usize,
+    /// 0-based offset in the cleaned nucleotide sequence where bases are
+    /// missing or extra.
+    pub nt_offset: usize,
+    pub kind: FrameshiftKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FrameshiftKind {
+    /// One or two bases are missing; the codon is padded with `!`.
+    Deletion { missing: usize },
+    /// Extra bases, left out of the codon alignment.
+    Insertion { bases: String },
+}
+
+impl Frameshift {
+    /// Bases gained (positive) or lost (negative) at this shift.
+    pub fn shift(&self) -> isize {
+        match &self.kind {
+            FrameshiftKind::Deletion { missing } => -(*missing as isize),
+            FrameshiftKind::Insertion { bases } => bases.len() as isize,
+        }
+    }
+}
+
+impl fmt::Display for Frameshift {
+    /// `column:shift`, such as `12:-1` or `40:+2`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{:+}", self.alignment_position, self.shift())
+    }
+}
+
+/// A record re-read with one codon, possibly padded with `!`, per residue.
+pub(crate) struct Realignment {
+    pub(crate) nucleotide: String,
+    /// `(residue index, nt offset, kind)` of each shift, in order.
+    pub(crate) shifts: Vec<(usize, usize, FrameshiftKind)>,
+}
+
+/// How one residue takes its bases: `taken` of them, arranged by `layout`.
+///
+/// With one or two bases, `layout` is where in the codon the first base
+/// (one base) or the `!` (two bases) goes. With four or five, it is where
+/// the run of extra bases starts among them.
+#[derive(Clone, Copy, Default)]
+struct Step {
+    taken: u8,
+    layout: u8,
+}
+
+impl Step {
+    fn taken(self) -> usize {
+        usize::from(self.taken)
+    }
+
+    fn layout(self) -> usize {
+        usize::from(self.layout)
+    }
+
+    fn layouts(taken: u8) -> u8 {
+        match taken {
+            3 => 1,
+            1 | 2 => 3,
+            _ => 4,
+        }
+    }
+
+    /// The codon the step writes, with `!` for missing bases.
+    fn codon(self, bases: &[u8]) -> [u8; 3] {
+        match self.taken() {
+            1 => {
+                let mut codon = *b"!!!";
+                codon[self.layout()] = bases[0];
+                codon
+            }
+            2 => {
+                let mut codon = *b"!!!";
+                let mut rest = bases.iter();
+                for (position, slot) in codon.iter_mut().enumerate() {
+                    if position != self.layout() {
+                        *slot = *rest.next().unwrap_or(&b'!');
+                    }
+                }
+                codon
+            }
+            3 => [bases[0], bases[1], bases[2]],
+            taken => {
+                let extra = self.layout()..self.layout() + taken - 3;
+                let mut codon = [0; 3];
+                let kept = (0..taken).filter(|index| !extra.contains(index));
+                for (slot, index) in codon.iter_mut().zip(kept) {
+                    *slot = bases[index];
+                }
+                codon
+            }
+        }
+    }
+
+    /// `(offset within the step, kind)` of the shift this step makes.
+    fn shift(self, bases: &[u8]) -> Option<(usize, FrameshiftKind)> {
+        match self.taken() {
+            3 => None,
+            1 | 2 => {
+                let codon = self.codon(bases);
+                let offset = codon.iter().position(|&base| base == b'!').unwrap_or(0);
+                Some((
+                    offset,
+                    FrameshiftKind::Deletion {
+                        missing: 3 - self.taken(),
+                    },
+                ))
+            }
+            taken => {
+                let extra = &bases[self.layout()..self.layout() + taken - 3];
+                Some((
+                    self.layout(),
+                    FrameshiftKind::Insertion {
+                        bases: String::from_utf8_lossy(extra).into_owned(),
+                    },
+                ))
+            }
+        }
+    }
+}
+
+/// Aligns `residues` (one byte each, gaps removed) to `nt`, allowing
+/// frameshifts. `encodes` tells whether a codon, with `N` standing in for
+/// missing bases, may encode a residue.
+///
+/// Returns `None` when no alignment fits in the band, or the band would
+/// need more than [`MAX_CELLS`] cells.
+pub(crate) fn realign<F>(
+    residues: &[u8],
+    nt: &[u8],
+    settings: FrameshiftAlignment,
+    encodes: F,
+) -> Option<Realignment>
+where
+    F: Fn(u8, &[u8; 3]) -> bool,
+{
+    let m = residues.len();
+    let len = nt.len();
+    if m == 0 {
+        return None;
+    }
+    let penalty = u64::from(settings.penalty.max(1));
+    let band = len.abs_diff(3 * m).checked_add(settings.band)?;
+    let width = band.checked_mul(2)?.checked_add(1)?;
+    if width.checked_mul(m + 1)? > MAX_CELLS {
+        return None;
+    }
+    // Cell of residue count `i` and bases taken `j`, if inside the band.
+    let cell = |i: usize, j: usize| -> Option<usize> {
+        let diagonal = (j + band).checked_sub(3 * i)?;
+        (diagonal < width).then_some(i * width + diagonal)
+    };
+
+    let mut cost = vec![UNREACHED; (m + 1) * width];
+    let mut back = vec![Step::default(); (m + 1) * width];
+    cost[cell(0, 0)?] = 0;
+
+    for (i, &aa) in residues.iter().enumerate() {
+        let first = (3 * i).saturating_sub(band);
+        let last = (3 * i + band).min(len);
+        for j in first..=last {
+            let Some(here) = cell(i, j) else {
+                continue;
+            };
+            if cost[here] == UNREACHED {
+                continue;
+            }
+            for taken in 1..=5 {
+                let end = j + usize::from(taken);
+                let Some(next) = cell(i + 1, end).filter(|_| end <= len) else {
+                    continue;
+                };
+                let bases = &nt[j..end];
+                for layout in 0..Step::layouts(taken) {
+                    let step = Step { taken, layout };
+                    let mut codon = step.codon(bases);
+                    for base in codon.iter_mut().filter(|base| **base == b'!') {
+                        *base = b'N';
+                    }
+                    let mut step_cost = cost[here] + u64::from(!encodes(aa, &codon));
+                    if taken != 3 {
+                        step_cost += penalty;
+                    }
+                    if step_cost < cost[next] {
+                        cost[next] = step_cost;
+                        back[next] = step;
+                    }
+                }
+            }
+        }
+    }
+
+    // One or two bases may be left over after the last codon.
+    let (end, _) = (len.saturating_sub(2)..=len)
+        .filter_map(|j| {
+            let total = cost[cell(m, j)?];
+            (total != UNREACHED).then(|| (j, total + if j < len { penalty } else { 0 }))
+        })
+        .min_by_key(|&(j, total)| (total, std::cmp::Reverse(j)))?;
+
+    let mut steps = vec![Step::default(); m];
+    let mut j = end;
+    for i in (1..=m).rev() {
+        let step = back[cell(i, j)?];
+        steps[i - 1] = step;
+        j -= step.taken();
+    }
+
+    let mut nucleotide = String::with_capacity(3 * m);
+    let mut shifts = Vec::new();
+    let mut j = 0;
+    for (i, step) in steps.into_iter().enumerate() {
+        let bases = &nt[j..j + step.taken()];
+        nucleotide.push_str(&String::from_utf8_lossy(&step.codon(bases)));
+        if let Some((offset, kind)) = step.shift(bases) {
+            shifts.push((i, j + offset, kind));
+        }
+        j += step.taken();
+    }
+    if end < len {
+        shifts.push((
+            m - 1,
+            end,
+            FrameshiftKind::Insertion {
+                bases: String::from_utf8_lossy(&nt[end..]).into_owned(),
+            },
+        ));
+    }
+    Some(Realignment { nucleotide, shifts })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{genetic_code, Ambiguity, CompiledSignature};
+
+    const AA: &[u8] = b"MKTAYIAKQRQISFVKSHFSRQLED";
+    const NT: &str = "ATGAAAACCGCCTATATTGCCAAACAGCGCCAGATTAGCTTTGTGAAAAGCCATTTTAGCCGCCAGCTGGAAGAT";
+
+    fn realign_standard(nt: &str, settings: FrameshiftAlignment) -> Option<Realignment> {
+        let signature = CompiledSignature::new(genetic_code(1).unwrap().amino_acids);
+        realign(AA, nt.as_bytes(), settings, |aa, codon| {
+            aa == b'X'
+                || signature
+                    .check(char::from(aa), codon, Ambiguity::Any)
+                    .is_match()
+        })
+    }
+
+    #[test]
+    fn places_a_single_deletion() {
+        let nt = format!("{}{}", &NT[..31], &NT[32..]);
+        let realignment = realign_standard(&nt, FrameshiftAlignment::default()).unwrap();
+        assert_eq!(
+            realignment.nucleotide,
+            format!("{}!{}", &NT[..31], &NT[32..])
+        );
+        assert_eq!(
+            realignment.shifts,
+            vec![(10, 31, FrameshiftKind::Deletion { missing: 1 })]
+        );
+    }
+
+    #[test]
+    fn places_a_single_insertion() {
+        let nt = format!("{}G{}", &NT[..46], &NT[46..]);
+        let realignment = realign_standard(&nt, FrameshiftAlignment::default()).unwrap();
+        assert_eq!(realignment.nucleotide, NT);
+        assert_eq!(
+            realignment.shifts,
+            vec![(
+                15,
+                46,
+                FrameshiftKind::Insertion {
+                    bases: "G".to_string()
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn leaves_an_in_frame_record_alone() {
+        let realignment = realign_standard(NT, FrameshiftAlignment::default()).unwrap();
+        assert_eq!(realignment.nucleotide, NT);
+        assert!(realignment.shifts.is_empty());
+    }
+
+    #[test]
+    fn returns_none_when_the_band_is_exhausted() {
+        // Three residues cannot take up 21 extra bases, two per shift.
+        let realignment = realign(
+            b"MKK",
+            &[b'A'; 30],
+            FrameshiftAlignment::default(),
+            |_, _| true,
+        );
+        assert!(realignment.is_none());
+
+        let huge = FrameshiftAlignment {
+            band: MAX_CELLS,
+            ..FrameshiftAlignment::default()
+        };
+        assert!(realign_standard(NT, huge).is_none());
+    }
+
+    #[test]
+    fn extreme_penalties_do_not_overflow() {
+        let nt = format!("{}{}", &NT[..31], &NT[32..]);
+        for penalty in [0, u32::MAX] {
+            let settings = FrameshiftAlignment {
+                penalty,
+                ..FrameshiftAlignment::default()
+            };
+            let realignment = realign_standard(&nt, settings).unwrap();
+            assert_eq!(realignment.shifts.len(), 1);
+        }
+    }
+}
@@ -0,0 +1,516 @@
+//! Up-front checks for caller-supplied [`GeneTable`]s.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::tables::codon_at;
+use crate::{
+    codon_index, for_each_expansion, has_iupac_match, iupac_triplet_index, iupac_triplet_masks,
+    normalize_base, Ambiguity, CodonMatch, CodonTable, CustomGeneticCode, GeneTable, Pn2CodonError,
+};
+
+/// One problem found by [`validate_gene_table`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GeneTableIssue {
+    /// A key that is not an amino acid symbol (`A` to `Z`, or `*` for stop).
+    InvalidAminoAcid { amino_acid: char },
+    /// A codon that is not three nucleotide or IUPAC ambiguity codes.
+    MalformedCodon { amino_acid: char, codon: String },
+    /// A codon with IUPAC ambiguity codes, such as `NNN`. It is kept as
+    /// written and only matches the same codes in a record.
+    AmbiguousCodon { amino_acid: char, codon: String },
+    /// A codon in lowercase or with `U`, read as `normalized`.
+    NonCanonicalCodon {
+        amino_acid: char,
+        codon: String,
+        normalized: String,
+    },
+    /// A codon listed more than once under the same amino acid.
+    RepeatedCodon { amino_acid: char, codon: String },
+    /// A codon listed under several amino acids. Ambiguity symbols (`B`,
+    /// `J`, `Z` and `X`) may share codons with the residues they stand for.
+    ConflictingCodon {
+        codon: String,
+        amino_acids: Vec<char>,
+    },
+    /// Codons no amino acid lists.
+    MissingCodons { codons: Vec<String> },
+}
+
+impl GeneTableIssue {
+    /// Errors make a table unusable; the other issues are warnings.
+    pub fn is_error(&self) -> bool {
+        matches!(
+            self,
+            GeneTableIssue::InvalidAminoAcid { .. }
+                | GeneTableIssue::MalformedCodon { .. }
+                | GeneTableIssue::ConflictingCodon { .. }
+        )
+    }
+}
+
+impl fmt::Display for GeneTableIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeneTableIssue::InvalidAminoAcid { amino_acid } => {
+                write!(f, "'{}' is not an amino acid symbol", amino_acid)
+            }
+            GeneTableIssue::MalformedCodon { amino_acid, codon } => write!(
+                f,
+                "'{}' listed under '{}' is not a codon of three nucleotide codes",
+                codon, amino_acid
+            ),
+            GeneTableIssue::AmbiguousCodon { amino_acid, codon } => write!(
+                f,
+                "'{}' listed under '{}' has ambiguity codes and only matches them literally",
+                codon, amino_acid
+            ),
+            GeneTableIssue::NonCanonicalCodon {
+                amino_acid,
+                codon,
+                normalized,
+            } => write!(
+                f,
+                "'{}' listed under '{}' is read as {}",
+                codon, amino_acid, normalized
+            ),
+            GeneTableIssue::RepeatedCodon { amino_acid, codon } => write!(
+                f,
+                "{} is listed more than once under '{}'",
+                codon, amino_acid
+            ),
+            GeneTableIssue::ConflictingCodon { codon, amino_acids } => write!(
+                f,
+                "{} is listed under {}",
+                codon,
+                amino_acids
+                    .iter()
+                    .map(|aa| format!("'{}'", aa))
+                    .collect::<Vec<String>>()
+                    .join(" and ")
+            ),
+            GeneTableIssue::MissingCodons { codons } => write!(
+                f,
+                "{} codon(s) are not listed: {}",
+                codons.len(),
+                codons.join(", ")
+            ),
+        }
+    }
+}
+
+/// The outcome of [`validate_gene_table`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GeneTableReport {
+    /// Problems in amino acid order, then codon order.
+    pub issues: Vec<GeneTableIssue>,
+    /// The table with codons uppercased, `U` read as `T` and repeats
+    /// dropped. Entries with errors are left out.
+    pub normalized: GeneTable,
+}
+
+impl GeneTableReport {
+    /// Whether the table has no errors; warnings are allowed.
+    pub fn is_valid(&self) -> bool {
+        !self.issues.iter().any(GeneTableIssue::is_error)
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &GeneTableIssue> {
+        self.issues.iter().filter(|issue| issue.is_error())
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &GeneTableIssue> {
+        self.issues.iter().filter(|issue| !issue.is_error())
+    }
+
+    /// The 64-character amino-acid signature of a valid table that lists
+    /// every codon, as used by [`CodonTable::Signature`].
+    pub fn signature(&self) -> Option<String> {
+        if !self.is_valid() {
+            return None;
+        }
+        // Ambiguity symbols only fill codons no residue lists.
+        let mut entries: Vec<(&char, &Vec<String>)> = self.normalized.iter().collect();
+        entries.sort_by_key(|(aa, _)| (!is_ambiguity_symbol(**aa), **aa));
+        let mut signature = ['\0'; 64];
+        for (aa, codons) in entries {
+            for codon in codons {
+                signature[codon_index(codon.as_bytes())?] = *aa;
+            }
+        }
+        if signature.contains(&'\0') {
+            return None;
+        }
+        Some(signature.iter().collect())
+    }
+
+    /// The table as a genetic code without alternative initiators, when
+    /// [`signature`](Self::signature) exists.
+    pub fn genetic_code(&self) -> Option<CustomGeneticCode> {
+        CustomGeneticCode::from_signature(&self.signature()?, None).ok()
+    }
+}
+
+impl fmt::Display for GeneTableReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.issues.is_empty() {
+            return write!(f, "no problems found");
+        }
+        let errors = self.errors().count();
+        write!(
+            f,
+            "{} error(s), {} warning(s)",
+            errors,
+            self.issues.len() - errors
+        )?;
+        for issue in &self.issues {
+            let level = if issue.is_error() { "error" } else { "warning" };
+            write!(f, "\n  {}: {}", level, issue)?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether `aa` stands for several residues rather than one.
+fn is_ambiguity_symbol(aa: char) -> bool {
+    matches!(aa, 'B' | 'J' | 'Z' | 'X')
+}
+
+/// Checks a custom codon table before anything is translated with it.
+///
+/// Codon case and `U`/`T` are normalized with a warning; so is a codon
+/// repeated under one amino acid, a codon with IUPAC ambiguity codes, which
+/// is matched literally, and so are unlisted codons, which simply never
+/// match. Symbols that are not amino acids, malformed codons and codons
+/// listed under two amino acids are errors; the ambiguity symbols `B`, `J`,
+/// `Z` and `X` may list codons of other residues.
+pub fn validate_gene_table(gene_table: &GeneTable) -> GeneTableReport {
+    let mut report = GeneTableReport::default();
+    let mut owners: BTreeMap<usize, Vec<char>> = BTreeMap::new();
+
+    let mut amino_acids: Vec<&char> = gene_table.keys().collect();
+    amino_acids.sort_unstable();
+    for &aa in amino_acids {
+        if !aa.is_ascii_uppercase() && aa != '*' {
+            report
+                .issues
+                .push(GeneTableIssue::InvalidAminoAcid { amino_acid: aa });
+            continue;
+        }
+        let mut normalized_codons: Vec<String> = Vec::new();
+        for codon in &gene_table[&aa] {
+            let Some(index) = codon_index(codon.as_bytes()) else {
+                if iupac_triplet_index(codon.as_bytes()).is_none() {
+                    report.issues.push(GeneTableIssue::MalformedCodon {
+                        amino_acid: aa,
+                        codon: codon.clone(),
+                    });
+                    continue;
+                }
+                let normalized: String = codon
+                    .bytes()
+                    .map(|base| char::from(normalize_base(base)))
+                    .collect();
+                report.issues.push(GeneTableIssue::AmbiguousCodon {
+                    amino_acid: aa,
+                    codon: normalized.clone(),
+                });
+                if normalized_codons.contains(&normalized) {
+                    report.issues.push(GeneTableIssue::RepeatedCodon {
+                        amino_acid: aa,
+                        codon: normalized,
+                    });
+                } else {
+                    normalized_codons.push(normalized);
+                }
+                continue;
+            };
+            let normalized = codon_at(index);
+            if *codon != normalized {
+                report.issues.push(GeneTableIssue::NonCanonicalCodon {
+                    amino_acid: aa,
+                    codon: codon.clone(),
+                    normalized: normalized.clone(),
+                });
+            }
+            if normalized_codons.contains(&normalized) {
+                report.issues.push(GeneTableIssue::RepeatedCodon {
+                    amino_acid: aa,
+                    codon: normalized,
+                });
+                continue;
+            }
+            owners.entry(index).or_default().push(aa);
+            normalized_codons.push(normalized);
+        }
+        report.normalized.insert(aa, normalized_codons);
+    }
+
+    for (index, aas) in &owners {
+        let residues: Vec<char> = aas
+            .iter()
+            .copied()
+            .filter(|aa| !is_ambiguity_symbol(*aa))
+            .collect();
+        if residues.len() > 1 {
+            report.issues.push(GeneTableIssue::ConflictingCodon {
+                codon: codon_at(*index),
+                amino_acids: residues,
+            });
+        }
+    }
+    let missing: Vec<String> = (0..64)
+        .filter(|index| !owners.contains_key(index))
+        .map(codon_at)
+        .collect();
+    if !missing.is_empty() {
+        report
+            .issues
+            .push(GeneTableIssue::MissingCodons { codons: missing });
+    }
+    report
+}
+
+impl CodonTable {
+    /// Validates a custom codon table and wraps its normalized form, or
+    /// fails with [`Pn2CodonError::InvalidGeneTable`] carrying the report.
+    /// `source` names the table in the error.
+    pub fn from_gene_table(gene_table: &GeneTable, source: &str) -> Result<Self, Pn2CodonError> {
+        let report = validate_gene_table(gene_table);
+        if !report.is_valid() {
+            return Err(Pn2CodonError::InvalidGeneTable {
+                source: source.to_string(),
+                message: report.to_string(),
+            });
+        }
+        Ok(CodonTable::Custom(report.normalized))
+    }
+}
+
+/// A [`GeneTable`] compiled for the alignment walkers, so a custom table is
+/// looked up by index just like a [`CodonTable::Signature`].
+///
+/// Each amino acid symbol owns one bit, in sorted symbol order. Concrete
+/// codons map to the symbols listing them, and every IUPAC triplet maps to
+/// the symbols listing any, and those listing all, of its expansions. Table
+/// entries the bitmasks cannot hold, such as codons with ambiguity codes or
+/// symbols past the 64th, are checked one by one.
+#[derive(Clone, Debug)]
+pub(crate) struct CompiledGeneTable {
+    symbols: Vec<char>,
+    codon_masks: [u64; 64],
+    /// Symbols listing any expansion of each IUPAC triplet.
+    any_masks: Box<[u64]>,
+    /// Symbols listing every expansion of each IUPAC triplet.
+    all_masks: Box<[u64]>,
+    literal: Vec<(char, String)>,
+}
+
+impl CompiledGeneTable {
+    pub(crate) fn new(gene_table: &GeneTable) -> Self {
+        let mut symbols: Vec<char> = gene_table.keys().copied().collect();
+        symbols.sort_unstable();
+
+        let mut codon_masks = [0_u64; 64];
+        let mut literal = Vec::new();
+        for (bit, aa) in symbols.iter().enumerate() {
+            for codon in &gene_table[aa] {
+                match codon_index(codon.as_bytes()) {
+                    Some(index) if bit < 64 => codon_masks[index] |= 1 << bit,
+                    _ => literal.push((*aa, codon.clone())),
+                }
+            }
+        }
+
+        let any_masks = iupac_triplet_masks(0, |codon| codon_masks[codon], |a, b| a | b);
+        let all_masks = iupac_triplet_masks(!0, |codon| codon_masks[codon], |a, b| a & b);
+
+        Self {
+            symbols,
+            codon_masks,
+            any_masks,
+            all_masks,
+            literal,
+        }
+    }
+
+    /// The table's amino acid symbols, sorted.
+    pub(crate) fn symbols(&self) -> &[char] {
+        &self.symbols
+    }
+
+    pub(crate) fn has_symbol(&self, aa: char) -> bool {
+        self.symbols.binary_search(&aa).is_ok()
+    }
+
+    fn mask(&self, codon: &[u8]) -> u64 {
+        match codon_index(codon) {
+            Some(index) => self.codon_masks[index],
+            None => iupac_triplet_index(codon).map_or(0, |index| self.any_masks[index]),
+        }
+    }
+
+    /// Whether `codon` encodes `aa`, reading ambiguity codes by `ambiguity`.
+    pub(crate) fn check(&self, aa: char, codon: &[u8], ambiguity: Ambiguity) -> CodonMatch {
+        let Ok(bit) = self.symbols.binary_search(&aa) else {
+            return CodonMatch::Mismatch;
+        };
+        if bit < 64 {
+            let bit = 1_u64 << bit;
+            if let Some(index) = codon_index(codon) {
+                if self.codon_masks[index] & bit != 0 {
+                    return CodonMatch::Exact;
+                }
+            } else if let Some(index) = iupac_triplet_index(codon) {
+                let encoded = match ambiguity {
+                    Ambiguity::Any => self.any_masks[index],
+                    Ambiguity::All => self.all_masks[index],
+                    Ambiguity::Reject => 0,
+                };
+                if encoded & bit != 0 {
+                    return CodonMatch::Ambiguous;
+                }
+            }
+        }
+        self.check_literal(aa, codon, ambiguity)
+    }
+
+    fn check_literal(&self, aa: char, codon: &[u8], ambiguity: Ambiguity) -> CodonMatch {
+        let Ok(text) = std::str::from_utf8(codon) else {
+            return CodonMatch::Mismatch;
+        };
+        let listed = || {
+            self.literal
+                .iter()
+                .filter(move |(symbol, _)| *symbol == aa)
+                .map(|(_, listed)| listed.as_str())
+        };
+        if listed().any(|listed| listed == text) {
+            return CodonMatch::Exact;
+        }
+        if !has_iupac_match(text, listed()) {
+            return CodonMatch::Mismatch;
+        }
+        if codon_index(codon).is_some() {
+            return CodonMatch::Exact;
+        }
+        let accepts = match ambiguity {
+            Ambiguity::Any => true,
+            Ambiguity::All => {
+                let mut all = true;
+                for_each_expansion(codon, |index| all &= self.lists(aa, index));
+                all
+            }
+            Ambiguity::Reject => false,
+        };
+        if accepts {
+            CodonMatch::Ambiguous
+        } else {
+            CodonMatch::Mismatch
+        }
+    }
+
+    /// Whether `codon` is a concrete codon listed under `*`. Codons with
+    /// ambiguity codes never are, whatever they might expand to.
+    pub(crate) fn is_stop(&self, codon: &[u8]) -> bool {
+        codon_index(codon).is_some_and(|index| self.lists('*', index))
+    }
+
+    /// Whether the concrete codon at `index` is listed under `aa`.
+    fn lists(&self, aa: char, index: usize) -> bool {
+        let in_masks = self
+            .symbols
+            .binary_search(&aa)
+            .is_ok_and(|bit| bit < 64 && self.codon_masks[index] & (1 << bit) != 0);
+        in_masks
+            || self.literal.iter().any(|(symbol, listed)| {
+                *symbol == aa && codon_index(listed.as_bytes()) == Some(index)
+            })
+    }
+
+    /// The first symbol, in sorted order, listing `codon`, preferring
+    /// residues over ambiguity symbols; `X` if none does.
+    pub(crate) fn translate(&self, codon: &[u8]) -> char {
+        let mask = self.mask(codon);
+        let from_mask = (0..self.symbols.len().min(64))
+            .filter(|bit| mask & (1 << bit) != 0)
+            .map(|bit| self.symbols[bit]);
+        let from_literal = self
+            .literal
+            .iter()
+            .filter(|(aa, _)| self.check_literal(*aa, codon, Ambiguity::Any).is_match())
+            .map(|(aa, _)| *aa);
+        from_mask
+            .chain(from_literal)
+            .min_by_key(|aa| (is_ambiguity_symbol(*aa), *aa))
+            .unwrap_or('X')
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(entries: &[(char, &[&str])]) -> GeneTable {
+        entries
+            .iter()
+            .map(|(aa, codons)| (*aa, codons.iter().map(|codon| codon.to_string()).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn ambiguity_symbols_may_share_codons() {
+        let gene_table = table(&[
+            ('N', &["AAT", "AAC"]),
+            ('D', &["GAT", "GAC"]),
+            ('B', &["AAT", "AAC", "GAT", "GAC"]),
+            ('X', &["AAT"]),
+        ]);
+        let report = validate_gene_table(&gene_table);
+        assert!(report.is_valid(), "{}", report);
+    }
+
+    #[test]
+    fn residues_may_not_share_codons() {
+        let gene_table = table(&[('N', &["AAT"]), ('D', &["AAT"]), ('B', &["AAT"])]);
+        let report = validate_gene_table(&gene_table);
+        assert_eq!(
+            report.errors().collect::<Vec<_>>(),
+            vec![&GeneTableIssue::ConflictingCodon {
+                codon: "AAT".to_string(),
+                amino_acids: vec!['D', 'N'],
+            }]
+        );
+    }
+
+    #[test]
+    fn ambiguous_codons_are_kept_with_a_warning() {
+        let gene_table = table(&[('M', &["ATG"]), ('X', &["nnn", "NNN"]), ('K', &["AAQ"])]);
+        let report = validate_gene_table(&gene_table);
+        assert_eq!(
+            report.errors().collect::<Vec<_>>(),
+            vec![&GeneTableIssue::MalformedCodon {
+                amino_acid: 'K',
+                codon: "AAQ".to_string(),
+            }]
+        );
+        assert!(report.warnings().any(|issue| issue
+            == &GeneTableIssue::AmbiguousCodon {
+                amino_acid: 'X',
+                codon: "NNN".to_string(),
+            }));
+        assert_eq!(report.normalized[&'X'], ["NNN"]);
+    }
+
+    #[test]
+    fn signature_prefers_residues_over_ambiguity_symbols() {
+        let code = crate::genetic_code(1).unwrap();
+        let mut gene_table = GeneTable::new();
+        for (index, aa) in code.amino_acids.chars().enumerate() {
+            gene_table.entry(aa).or_default().push(codon_at(index));
+        }
+        gene_table.insert('X', (0..64).map(codon_at).collect());
+        let report = validate_gene_table(&gene_table);
+        assert_eq!(report.signature().as_deref(), Some(code.amino_acids));
+    }
+}
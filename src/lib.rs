@@ -1,56 +1,49 @@
-use pyo3::exceptions::PyValueError;
-use pyo3::prelude::*;
-use std::cell::{Cell, RefCell};
+use std::borrow::Cow;
+use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::Write;
 use std::path::Path;
 use std::sync::Arc;
 
-type GeneTable = HashMap<char, Vec<String>>;
+use rayon::prelude::*;
+
+pub mod batch;
+pub mod code_file;
+mod error;
+pub mod fasta;
+pub mod frameshift;
+pub mod gene_table;
+pub mod pairing;
+#[cfg(feature = "python")]
+mod python;
+pub mod tables;
+pub mod translate;
+
+pub use batch::{discover_jobs, read_manifest, BatchJob, BatchOptions, BatchOutcome, BatchSummary};
+pub use code_file::{
+    genetic_code_from_json, parse_gc_prt, read_genetic_codes, select_genetic_code,
+    CustomGeneticCode,
+};
+pub use error::{PairingIssues, Pn2CodonError, RecordContext};
+pub use fasta::{read_fasta, read_paired_records, write_output, Compression, FastaRecord};
+pub use frameshift::{Frameshift, FrameshiftAlignment, FrameshiftKind};
+use gene_table::CompiledGeneTable;
+pub use gene_table::{validate_gene_table, GeneTableIssue, GeneTableReport};
+pub use pairing::{pair_records_by_id, IdExtractor, Pairing};
+pub use tables::{genetic_code, genetic_code_by_name, GeneticCode, GENETIC_CODES};
+pub use translate::{
+    reverse_complement, translate, translate_batch, PartialCodon, TranslateOptions,
+};
+
+/// Custom codon table: amino acid symbol to the codons that encode it.
+pub type GeneTable = HashMap<char, Vec<String>>;
 
 const VALID_PEPS: &[char] = &[
     'A', 'L', 'W', 'Q', 'Y', 'E', 'C', 'D', 'F', 'G', 'H', 'I', 'M', 'K', 'P', 'R', 'S', 'V',
     'N', 'T', '*', '-', 'B', 'J', 'Z', 'X',
 ];
 
-const TABLE_DATA: &[(i32, &str)] = &[
-    (1, "FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
-    (2, "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSS**VVVVAAAADDEEGGGG"),
-    (3, "FFLLSSSSYY**CCWWTTTTPPPPHHQQRRRRIIMMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
-    (4, "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
-    (5, "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSSSSVVVVAAAADDEEGGGG"),
-    (6, "FFLLSSSSYYQQCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
-    (9, "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNNKSSSSVVVVAAAADDEEGGGG"),
-    (10, "FFLLSSSSYY**CCCWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
-    (11, "FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
-    (12, "FFLLSSSSYY**CC*WLLLSPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
-    (13, "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSSGGVVVVAAAADDEEGGGG"),
-    (14, "FFLLSSSSYYY*CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNNKSSSSVVVVAAAADDEEGGGG"),
-    (15, "FFLLSSSSYY*QCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
-    (16, "FFLLSSSSYY*LCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
-    (21, "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNNKSSSSVVVVAAAADDEEGGGG"),
-    (22, "FFLLSS*SYY*LCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
-    (23, "FF*LSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
-    (24, "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSSKVVVVAAAADDEEGGGG"),
-    (25, "FFLLSSSSYY**CCGWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
-    (26, "FFLLSSSSYY**CC*WLLLAPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
-    (27, "FFLLSSSSYYQQCCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
-    (28, "FFLLSSSSYYQQCCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
-    (29, "FFLLSSSSYYYYCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
-    (30, "FFLLSSSSYYEECC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
-    (31, "FFLLSSSSYYEECCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
-    (32, "FFLLSSSSYY*WCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
-    (33, "FFLLSSSSYYY*CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSSKVVVVAAAADDEEGGGG"),
-];
-
-fn table_signature(table_num: i32) -> Option<&'static str> {
-    for (id, signature) in TABLE_DATA {
-        if *id == table_num {
-            return Some(*signature);
-        }
-    }
-    None
-}
-
 fn normalize_base(base: u8) -> u8 {
     match base.to_ascii_uppercase() {
         b'U' => b'T',
@@ -92,22 +85,24 @@ fn codon_index(codon: &[u8]) -> Option<usize> {
     Some(base_rank(codon[0])? * 16 + base_rank(codon[1])? * 4 + base_rank(codon[2])?)
 }
 
-fn aa_matches_table_symbol(table_aa: u8, aa: char) -> bool {
-    match aa {
-        'B' => table_aa == b'N' || table_aa == b'D',
-        'J' => table_aa == b'L' || table_aa == b'I',
-        'Z' => table_aa == b'Q' || table_aa == b'E',
-        _ => table_aa == aa as u8,
+/// Bit of a signature symbol (`A` to `Z`, or `*`) in a [`CompiledSignature`]
+/// mask.
+fn symbol_bit(symbol: u8) -> u32 {
+    match symbol {
+        b'A'..=b'Z' => 1 << (symbol - b'A'),
+        b'*' => 1 << 26,
+        _ => 0,
     }
 }
 
-fn codon_matches_signature(signature: &str, aa: char, codon: &str) -> bool {
-    match codon_index(codon.as_bytes()) {
-        Some(idx) => match signature.as_bytes().get(idx) {
-            Some(table_aa) => aa_matches_table_symbol(*table_aa, aa),
-            None => false,
-        },
-        None => false,
+/// The signature symbols a peptide residue accepts; the ambiguous residues
+/// `B`, `J` and `Z` accept either of their amino acids.
+fn residue_mask(aa: char) -> u32 {
+    match aa {
+        'B' => symbol_bit(b'N') | symbol_bit(b'D'),
+        'J' => symbol_bit(b'L') | symbol_bit(b'I'),
+        'Z' => symbol_bit(b'Q') | symbol_bit(b'E'),
+        _ => u8::try_from(aa).map_or(0, symbol_bit),
     }
 }
 
@@ -194,41 +189,146 @@ fn fill_possible_bases(base: u8, out: &mut [u8; 4]) -> usize {
     }
 }
 
-fn ambiguous_triplet_matches_signature(signature: &str, aa: char, triplet: &str) -> bool {
-    let bytes = triplet.as_bytes();
-    if bytes.len() != 3 {
-        return false;
-    }
+/// Bases with an IUPAC code, in [`iupac_triplet_index`] order.
+const IUPAC_BASES: &[u8; 15] = b"ACGTRYSWKMBDHVN";
 
-    let signature_bytes = signature.as_bytes();
-    let mut p0 = [0_u8; 4];
-    let mut p1 = [0_u8; 4];
-    let mut p2 = [0_u8; 4];
-    let l0 = fill_possible_bases(bytes[0], &mut p0);
-    let l1 = fill_possible_bases(bytes[1], &mut p1);
-    let l2 = fill_possible_bases(bytes[2], &mut p2);
+fn iupac_code(base: u8) -> Option<usize> {
+    let base = normalize_base(base);
+    IUPAC_BASES.iter().position(|&code| code == base)
+}
 
-    if l0 == 0 || l1 == 0 || l2 == 0 {
-        return false;
+/// Index of a triplet of IUPAC codes among all 15 x 15 x 15 of them.
+fn iupac_triplet_index(triplet: &[u8]) -> Option<usize> {
+    if triplet.len() != 3 {
+        return None;
     }
+    Some(iupac_code(triplet[0])? * 225 + iupac_code(triplet[1])? * 15 + iupac_code(triplet[2])?)
+}
 
-    for b0 in p0.iter().take(l0) {
-        for b1 in p1.iter().take(l1) {
-            for b2 in p2.iter().take(l2) {
-                let idx = match codon_index(&[*b0, *b1, *b2]) {
-                    Some(i) => i,
-                    None => continue,
-                };
-                if let Some(table_aa) = signature_bytes.get(idx) {
-                    if aa_matches_table_symbol(*table_aa, aa) {
-                        return true;
-                    }
+/// Calls `f` with the index of every concrete codon `triplet` expands to.
+fn for_each_expansion<F: FnMut(usize)>(triplet: &[u8], mut f: F) {
+    if triplet.len() != 3 {
+        return;
+    }
+    let mut expansions = [[0_u8; 4]; 3];
+    let mut lengths = [0_usize; 3];
+    for position in 0..3 {
+        lengths[position] = fill_possible_bases(triplet[position], &mut expansions[position]);
+    }
+    for b0 in &expansions[0][..lengths[0]] {
+        for b1 in &expansions[1][..lengths[1]] {
+            for b2 in &expansions[2][..lengths[2]] {
+                if let Some(codon) = codon_index(&[*b0, *b1, *b2]) {
+                    f(codon);
                 }
             }
         }
     }
+}
 
-    false
+/// For every IUPAC triplet, `codon_mask` of the concrete codons it expands
+/// to folded with `combine`, indexed by [`iupac_triplet_index`].
+fn iupac_triplet_masks<T, F, C>(empty: T, codon_mask: F, combine: C) -> Box<[T]>
+where
+    T: Copy,
+    F: Fn(usize) -> T,
+    C: Fn(T, T) -> T,
+{
+    let mut masks = vec![empty; IUPAC_BASES.len().pow(3)].into_boxed_slice();
+    for (index, mask) in masks.iter_mut().enumerate() {
+        let triplet = [
+            IUPAC_BASES[index / 225],
+            IUPAC_BASES[index / 15 % 15],
+            IUPAC_BASES[index % 15],
+        ];
+        for_each_expansion(&triplet, |codon| *mask = combine(*mask, codon_mask(codon)));
+    }
+    masks
+}
+
+/// How a codon was found to relate to a residue.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CodonMatch {
+    Mismatch,
+    /// The codon, read as is, encodes the residue.
+    Exact,
+    /// The codon has ambiguity codes and the [`Ambiguity`] policy accepts it.
+    Ambiguous,
+}
+
+impl CodonMatch {
+    fn is_match(self) -> bool {
+        self != CodonMatch::Mismatch
+    }
+}
+
+/// A 64-character signature (amino acids or initiation codons of a
+/// [`CodonTable::Signature`]) compiled for the codon walk, so checking a
+/// codon, ambiguity codes included, is a single lookup.
+#[derive(Clone, Debug)]
+struct CompiledSignature {
+    signature: Box<[u8]>,
+    /// Symbols encoded by the expansions of each IUPAC triplet, with
+    /// [`UNENCODED`](Self::UNENCODED) set when some expansion has none.
+    triplet_masks: Box<[u32]>,
+}
+
+impl CompiledSignature {
+    const UNENCODED: u32 = 1 << 31;
+
+    fn new(signature: &str) -> Self {
+        let signature: Box<[u8]> = signature.as_bytes().into();
+        let triplet_masks = iupac_triplet_masks(
+            0,
+            |codon| match signature.get(codon).map_or(0, |symbol| symbol_bit(*symbol)) {
+                0 => Self::UNENCODED,
+                bit => bit,
+            },
+            |a, b| a | b,
+        );
+        Self {
+            signature,
+            triplet_masks,
+        }
+    }
+
+    /// Whether `codon` encodes `aa`, reading ambiguity codes by `ambiguity`.
+    fn check(&self, aa: char, codon: &[u8], ambiguity: Ambiguity) -> CodonMatch {
+        let accepted = residue_mask(aa);
+        if let Some(index) = codon_index(codon) {
+            return match self.signature.get(index) {
+                Some(symbol) if symbol_bit(*symbol) & accepted != 0 => CodonMatch::Exact,
+                _ => CodonMatch::Mismatch,
+            };
+        }
+        let Some(index) = iupac_triplet_index(codon) else {
+            return CodonMatch::Mismatch;
+        };
+        let encoded = self.triplet_masks[index];
+        let accepts = match ambiguity {
+            Ambiguity::Any => encoded & accepted != 0,
+            Ambiguity::All => encoded & !accepted == 0,
+            Ambiguity::Reject => false,
+        };
+        if accepts {
+            CodonMatch::Ambiguous
+        } else {
+            CodonMatch::Mismatch
+        }
+    }
+
+    /// Whether `codon` is a concrete stop codon. Codons with ambiguity
+    /// codes never are, whatever they might expand to.
+    fn is_stop(&self, codon: &[u8]) -> bool {
+        codon_index(codon).and_then(|index| self.signature.get(index)) == Some(&b'*')
+    }
+
+    /// The symbol of a concrete codon; `X` for anything else.
+    fn translate(&self, codon: &[u8]) -> char {
+        codon_index(codon)
+            .and_then(|index| self.signature.get(index))
+            .map_or('X', |symbol| char::from(*symbol))
+    }
 }
 
 fn recurse(triplet: &[u8], working: &mut [u8], index: usize, output: &mut HashSet<String>) {
@@ -300,7 +400,6 @@ where
     false
 }
 
-#[pyfunction]
 pub fn attempt_iupac_substitution(original_triplet: &str, taxa: Vec<String>) -> Option<String> {
     let possible_subs = make_iupac_set(original_triplet.as_bytes());
     for triplet in taxa {
@@ -312,7 +411,7 @@ pub fn attempt_iupac_substitution(original_triplet: &str, taxa: Vec<String>) ->
 }
 
 #[derive(Clone)]
-struct AminoAcidTranslator {
+struct AminoAcidTranslator<'a> {
     sequence_index: usize,
     aa_source_label: Arc<str>,
     aa_header: String,
@@ -320,11 +419,13 @@ struct AminoAcidTranslator {
     nt_source_label: Arc<str>,
     nt_header: String,
     nucleotide: String,
-    has_reported_error: Cell<bool>,
-    error_message: RefCell<Option<String>>,
+    options: &'a AlignOptions,
+    collect_all: bool,
+    errors: RefCell<Vec<Pn2CodonError>>,
+    metadata: RecordMetadata,
 }
 
-fn truncate_header(header: &str) -> String {
+pub(crate) fn truncate_header(header: &str) -> String {
     if header.len() > 150 {
         format!("{}...", &header[0..150])
     } else {
@@ -332,7 +433,7 @@ fn truncate_header(header: &str) -> String {
     }
 }
 
-fn source_label_from_path(path: &str, fallback: &str) -> String {
+pub(crate) fn source_label_from_path(path: &str, fallback: &str) -> String {
     let trimmed = path.trim();
     if trimmed.is_empty() {
         return fallback.to_string();
@@ -344,17 +445,31 @@ fn source_label_from_path(path: &str, fallback: &str) -> String {
         .filter(|value| !value.is_empty())
         .unwrap_or(trimmed);
 
-    if file_name.to_ascii_lowercase().ends_with(".fa") {
+    let lower = file_name.to_ascii_lowercase();
+    let compression_suffix = COMPRESSED_SUFFIXES
+        .iter()
+        .find(|suffix| lower.ends_with(*suffix))
+        .map_or(0, |suffix| suffix.len());
+    let (stem, compressed) = file_name.split_at(file_name.len() - compression_suffix);
+
+    if stem.to_ascii_lowercase().ends_with(".fa") {
         file_name.to_string()
     } else {
-        format!("{}.fa", file_name)
+        format!("{}.fa{}", stem, compressed)
     }
 }
 
-fn format_error_block(title: &str, details: &str) -> String {
+/// File suffixes of gzip/bgzip-compressed inputs.
+const COMPRESSED_SUFFIXES: &[&str] = &[".gz", ".bgz", ".bgzf"];
+
+pub(crate) fn format_error_block(title: &str, details: &str) -> String {
+    format_block("ERROR", title, details)
+}
+
+fn format_block(level: &str, title: &str, details: &str) -> String {
     let mut out = String::new();
     out.push_str("========================================\n");
-    out.push_str(&format!("pn2codon ERROR: {}\n", title));
+    out.push_str(&format!("pn2codon {}: {}\n", level, title));
     out.push_str("========================================\n");
     if !details.is_empty() {
         out.push_str(details);
@@ -379,8 +494,19 @@ fn clamp_window(len: usize, center: usize, radius: usize) -> (usize, usize) {
 #[derive(Clone, Copy)]
 enum NtTrackMode<'a> {
     None,
-    Signature(&'a str),
-    Table(&'a GeneTable),
+    Signature(&'a CompiledSignature, Ambiguity),
+    Table(&'a CompiledGeneTable, Ambiguity),
+}
+
+impl NtTrackMode<'_> {
+    /// The same table under another ambiguity policy.
+    fn with_ambiguity(self, ambiguity: Ambiguity) -> Self {
+        match self {
+            NtTrackMode::None => NtTrackMode::None,
+            NtTrackMode::Signature(signature, _) => NtTrackMode::Signature(signature, ambiguity),
+            NtTrackMode::Table(gene_table, _) => NtTrackMode::Table(gene_table, ambiguity),
+        }
+    }
 }
 
 fn spaced_aa_track(aas: &str) -> String {
@@ -399,70 +525,47 @@ fn spaced_aa_track(aas: &str) -> String {
     out
 }
 
-fn translate_nt_window_with_signature(signature: &str, nt_window: &str) -> String {
-    let mut out = String::new();
-    for chunk in nt_window.as_bytes().chunks(3) {
-        if chunk.len() != 3 {
-            break;
-        }
-
-        let aa = codon_index(chunk)
-            .and_then(|idx| signature.as_bytes().get(idx).copied())
-            .map(char::from)
-            .unwrap_or('X');
-        out.push(aa);
-    }
-    out
+fn translate_nt_window_with_signature(signature: &CompiledSignature, nt_window: &str) -> String {
+    nt_window
+        .as_bytes()
+        .chunks_exact(3)
+        .map(|codon| signature.translate(codon))
+        .collect()
 }
 
-fn translate_nt_window_with_table(gene_table: &GeneTable, nt_window: &str) -> String {
-    let mut aa_keys: Vec<char> = gene_table.keys().copied().collect();
-    aa_keys.sort_unstable();
+fn translate_nt_window_with_table(gene_table: &CompiledGeneTable, nt_window: &str) -> String {
+    nt_window
+        .as_bytes()
+        .chunks_exact(3)
+        .map(|codon| gene_table.translate(codon))
+        .collect()
+}
 
-    let mut out = String::new();
-    for chunk in nt_window.as_bytes().chunks(3) {
-        if chunk.len() != 3 {
-            break;
+fn codon_matches_expected_in_mode(nt_track_mode: NtTrackMode<'_>, expected_aa: char, codon: &[u8]) -> bool {
+    match nt_track_mode {
+        NtTrackMode::None => true,
+        NtTrackMode::Signature(signature, ambiguity) => {
+            signature.check(expected_aa, codon, ambiguity).is_match()
         }
-
-        let codon = match std::str::from_utf8(chunk) {
-            Ok(value) => value,
-            Err(_) => {
-                out.push('X');
-                continue;
-            }
-        };
-
-        let mut mapped = 'X';
-        for aa in aa_keys.iter() {
-            if let Some(codons) = gene_table.get(aa) {
-                if codons.iter().any(|triplet| triplet == codon)
-                    || has_iupac_match(codon, codons.iter().map(String::as_str))
-                {
-                    mapped = *aa;
-                    break;
-                }
-            }
+        NtTrackMode::Table(gene_table, ambiguity) => {
+            gene_table.check(expected_aa, codon, ambiguity).is_match()
         }
-        out.push(mapped);
     }
-    out
 }
 
-fn codon_matches_expected_in_mode(nt_track_mode: NtTrackMode<'_>, expected_aa: char, codon: &str) -> bool {
+/// Whether the walkers take `codon` under `aa` without checking it: `X`
+/// residues, codons holding a frameshift (`!`) and, under
+/// [`Ambiguity::Any`], codons containing `N`.
+fn passes_unchecked(aa: char, codon: &[u8], ambiguity: Ambiguity) -> bool {
+    aa == 'X' || codon.contains(&b'!') || (ambiguity == Ambiguity::Any && codon.contains(&b'N'))
+}
+
+/// Whether `codon` is a concrete stop codon of the table in use.
+fn codon_is_stop(nt_track_mode: NtTrackMode<'_>, codon: &[u8]) -> bool {
     match nt_track_mode {
-        NtTrackMode::None => true,
-        NtTrackMode::Signature(signature) => {
-            codon_matches_signature(signature, expected_aa, codon)
-                || ambiguous_triplet_matches_signature(signature, expected_aa, codon)
-        }
-        NtTrackMode::Table(gene_table) => match gene_table.get(&expected_aa) {
-            Some(taxa) => {
-                taxa.iter().any(|triplet| triplet == codon)
-                    || has_iupac_match(codon, taxa.iter().map(String::as_str))
-            }
-            None => false,
-        },
+        NtTrackMode::None => false,
+        NtTrackMode::Signature(signature, _) => signature.is_stop(codon),
+        NtTrackMode::Table(gene_table, _) => gene_table.is_stop(codon),
     }
 }
 
@@ -475,6 +578,7 @@ fn residue_index_for_alignment_position(aligned_aa: &str, aa_alignment_index: us
     residue_count.saturating_sub(1)
 }
 
+#[allow(clippy::too_many_arguments)]
 fn format_seq_inconsistency_details(
     aa_source_label: &str,
     aa_id: &str,
@@ -553,8 +657,12 @@ fn format_seq_inconsistency_details(
 
         let translated_nt_aas = match nt_track_mode {
             NtTrackMode::None => String::new(),
-            NtTrackMode::Signature(signature) => translate_nt_window_with_signature(signature, nt_window),
-            NtTrackMode::Table(gene_table) => translate_nt_window_with_table(gene_table, nt_window),
+            NtTrackMode::Signature(signature, _) => {
+                translate_nt_window_with_signature(signature, nt_window)
+            }
+            NtTrackMode::Table(gene_table, _) => {
+                translate_nt_window_with_table(gene_table, nt_window)
+            }
         };
 
         let nt_track = spaced_aa_track(&translated_nt_aas);
@@ -569,18 +677,17 @@ fn format_seq_inconsistency_details(
             let codon_count = expected_aas.len().min(nt_window.len() / 3);
             let mut markers = vec![' '; nt_window.len()];
 
-            for codon_idx in 0..codon_count {
+            for (codon_idx, expected_aa) in expected_aas.iter().copied().enumerate().take(codon_count) {
                 let codon_start = codon_idx * 3;
                 let codon = &nt_window[codon_start..codon_start + 3];
-                let expected_aa = expected_aas[codon_idx];
-                if !codon_matches_expected_in_mode(nt_track_mode, expected_aa, codon) {
+                if !codon_matches_expected_in_mode(nt_track_mode, expected_aa, codon.as_bytes()) {
                     markers[codon_start] = '_';
                     markers[codon_start + 1] = '_';
                     markers[codon_start + 2] = '_';
                 }
             }
 
-            if markers.iter().any(|c| *c == '_') {
+            if markers.contains(&'_') {
                 let marker_line: String = markers.into_iter().collect();
                 out.push_str(&" ".repeat(nt_prefix.len()));
                 out.push_str(&marker_line);
@@ -591,54 +698,49 @@ fn format_seq_inconsistency_details(
     out
 }
 
-fn supported_table_numbers() -> String {
-    TABLE_DATA
-        .iter()
-        .map(|(id, _)| id.to_string())
-        .collect::<Vec<String>>()
-        .join(", ")
-}
-
-impl AminoAcidTranslator {
+impl<'a> AminoAcidTranslator<'a> {
     fn new(
         sequence_index: usize,
         aa_source_label: Arc<str>,
-        aa_header: String,
-        amino_acid: String,
         nt_source_label: Arc<str>,
-        nt_header: String,
-        nucleotide: String,
+        record: PairedRecord,
+        options: &'a AlignOptions,
     ) -> Self {
         Self {
             sequence_index,
             aa_source_label,
-            aa_header,
-            amino_acid,
+            aa_header: record.aa_header,
+            amino_acid: record.aa,
             nt_source_label,
-            nt_header,
-            nucleotide,
-            has_reported_error: Cell::new(false),
-            error_message: RefCell::new(None),
+            nt_header: record.nt_header,
+            nucleotide: record.nt,
+            options,
+            collect_all: false,
+            errors: RefCell::new(Vec::new()),
+            metadata: RecordMetadata::default(),
         }
     }
 
+    fn record_context(&self) -> Box<RecordContext> {
+        Box::new(RecordContext {
+            sequence_index: self.sequence_index,
+            aa_source: self.aa_source_label.to_string(),
+            aa_header: self.aa_header.clone(),
+            nt_source: self.nt_source_label.to_string(),
+            nt_header: self.nt_header.clone(),
+        })
+    }
+
     fn do_checks(&self) {
-        if self.aa_header != self.nt_header {
-            let details = format!(
-                "Sequence index: {}\nExpected header ({}): \"{}\"\nheader found ({}): \"{}\"",
-                self.sequence_index,
-                self.aa_source_label.as_ref(),
-                truncate_header(&self.aa_header),
-                self.nt_source_label.as_ref(),
-                truncate_header(&self.nt_header)
-            );
-            self.report_error(format_error_block(
-                "Header mismatch between peptide and nucleotide records.",
-                &details,
-            ));
+        let id_extractor = self.options.pairing.id_extractor();
+        if id_extractor.extract(&self.aa_header) != id_extractor.extract(&self.nt_header) {
+            self.report_error(Pn2CodonError::HeaderMismatch {
+                record: self.record_context(),
+            });
         }
 
-        let aa_triplet_len = self.amino_acid.chars().filter(|c| *c != '-').count() * 3;
+        let residues = self.amino_acid.chars().filter(|c| *c != '-').count();
+        let aa_triplet_len = residues * 3;
         let nt_len = self.nucleotide.len();
 
         if nt_len != aa_triplet_len {
@@ -664,13 +766,27 @@ impl AminoAcidTranslator {
                 NtTrackMode::None,
                 None,
             );
-            self.report_error(format_error_block(
-                "Peptide and nucleotide lengths are inconsistent.",
-                &details,
-            ));
+            self.report_error(Pn2CodonError::LengthMismatch {
+                record: self.record_context(),
+                residues,
+                nt_len,
+                details,
+            });
         }
     }
 
+    /// Whether the codon walk can still produce meaningful diagnostics after
+    /// `do_checks`: a header mismatch does not affect the codons, a length
+    /// mismatch does.
+    fn can_walk(&self) -> bool {
+        self.collect_all
+            && !self
+                .errors
+                .borrow()
+                .iter()
+                .any(|error| matches!(error, Pn2CodonError::LengthMismatch { .. }))
+    }
+
     fn streamline(&mut self) {
         self.amino_acid = self
             .amino_acid
@@ -686,32 +802,360 @@ impl AminoAcidTranslator {
             })
             .collect();
 
-        self.nucleotide = self
-            .nucleotide
-            .trim()
-            .to_ascii_uppercase()
-            .replace('-', "")
-            .replace('.', "");
+        let trimmed = self.nucleotide.trim();
+        if trimmed.len() != self.nucleotide.len() {
+            self.nucleotide = trimmed.to_string();
+        }
+        self.nucleotide.make_ascii_uppercase();
+        self.nucleotide.retain(|c| c != '-' && c != '.');
+    }
+
+    /// The residues that consume a codon, in order.
+    fn residues(&self) -> Vec<char> {
+        self.amino_acid
+            .chars()
+            .filter(|c| *c != '-' && !c.is_ascii_digit())
+            .collect()
     }
 
-    fn report_error(&self, message: String) {
-        if self.has_reported_error.replace(true) {
+    /// How many `residues` the codons of `nt` encode. `X` never counts, so
+    /// a perfect score is the number of non-`X` residues.
+    fn codon_score(residues: &[char], nt: &[u8], nt_track_mode: NtTrackMode<'_>) -> usize {
+        residues
+            .iter()
+            .zip(nt.chunks_exact(3))
+            .filter(|(aa, codon)| {
+                **aa != 'X' && codon_matches_expected_in_mode(nt_track_mode, **aa, codon)
+            })
+            .count()
+    }
+
+    /// Re-reads the nucleotide sequence in whichever of its six frames
+    /// explains the most peptide residues, and records that frame.
+    ///
+    /// The first forward frame wins ties and is kept without trying the
+    /// others when it already explains every residue. Other frames drop the
+    /// skipped leading bases and any trailing partial codon. A record with
+    /// non-ASCII characters is left as given, for the walk to report.
+    fn detect_frame(&mut self, nt_track_mode: NtTrackMode<'_>) {
+        if !self.nucleotide.is_ascii() {
             return;
         }
-        self.error_message.replace(Some(message));
+        let residues = self.residues();
+        let perfect = residues.iter().filter(|aa| **aa != 'X').count();
+        let score = |nt: &str| Self::codon_score(&residues, nt.as_bytes(), nt_track_mode);
+
+        let forward = std::mem::take(&mut self.nucleotide);
+        let mut best = (Frame::FORWARD, score(&forward));
+        if best.1 < perfect {
+            let reverse = reverse_complement(&forward);
+            for (strand, nt) in [(Strand::Forward, &forward), (Strand::Reverse, &reverse)] {
+                for offset in 0..3 {
+                    let frame = Frame { strand, offset };
+                    let frame_score = score(nt.get(offset..).unwrap_or_default());
+                    if frame_score > best.1 {
+                        best = (frame, frame_score);
+                    }
+                }
+            }
+        }
+
+        let frame = best.0;
+        self.nucleotide = if frame == Frame::FORWARD {
+            forward
+        } else {
+            let nt = match frame.strand {
+                Strand::Forward => forward,
+                Strand::Reverse => reverse_complement(&forward),
+            };
+            let framed = nt.get(frame.offset..).unwrap_or_default();
+            framed[..framed.len() / 3 * 3].to_string()
+        };
+        self.metadata.frame = Some(frame);
     }
 
-    fn get_error_message(&self) -> Option<String> {
-        self.error_message.borrow().clone()
+    /// Cuts a nucleotide sequence longer than the peptide needs down to the
+    /// window that explains the most residues, dropping the untranslated
+    /// flanks on either side. Every start offset is tried, so this also
+    /// finds the frame; with frame detection the reverse strand is searched
+    /// too. The earliest best window wins, and the search stops at the first
+    /// window that explains every residue. The search runs on bytes; the
+    /// reverse strand is only searched for ASCII records, and a best window
+    /// with non-ASCII characters is not cut out.
+    ///
+    /// Returns `false`, changing nothing, when there are no flanks to trim.
+    fn trim_flanks(&mut self, nt_track_mode: NtTrackMode<'_>) -> bool {
+        let residues = self.residues();
+        let needed = residues.len() * 3;
+        let nt_len = self.nucleotide.len();
+        if nt_len <= needed {
+            return false;
+        }
+        let perfect = residues.iter().filter(|aa| **aa != 'X').count();
+
+        let forward = std::mem::take(&mut self.nucleotide);
+        let mut strands = vec![(Strand::Forward, forward)];
+        if self.options.detect_frame && strands[0].1.is_ascii() {
+            let reverse = reverse_complement(&strands[0].1);
+            strands.push((Strand::Reverse, reverse));
+        }
+
+        // (score, strand index, start)
+        let mut best = (0, 0, 0);
+        'search: for (strand_index, (_, nt)) in strands.iter().enumerate() {
+            for start in 0..=nt_len - needed {
+                let window = &nt.as_bytes()[start..start + needed];
+                let score = Self::codon_score(&residues, window, nt_track_mode);
+                if score > best.0 || (strand_index, start) == (0, 0) {
+                    best = (score, strand_index, start);
+                }
+                if score == perfect {
+                    break 'search;
+                }
+            }
+        }
+
+        let (_, strand_index, start) = best;
+        let (strand, nt) = strands.swap_remove(strand_index);
+        let window = &nt.as_bytes()[start..start + needed];
+        if !window.is_ascii() {
+            // Only the forward strand is searched for such a record.
+            self.nucleotide = nt;
+            return false;
+        }
+        self.nucleotide = String::from_utf8_lossy(window).into_owned();
+        // Coordinates are always given on the record as supplied.
+        self.metadata.coding_region = Some(match strand {
+            Strand::Forward => start..start + needed,
+            Strand::Reverse => nt_len - start - needed..nt_len - start,
+        });
+        if self.options.detect_frame {
+            self.metadata.frame = Some(Frame {
+                strand,
+                offset: start % 3,
+            });
+        }
+        true
     }
 
-    fn error_out_mismatch(&self, aa_index: usize, nt_base_index: usize, nt_track_mode: NtTrackMode<'_>) {
+    /// Sets a terminal stop codon that the peptide does not show aside, so
+    /// the length check passes. Only applies when the nucleotide record is
+    /// exactly one codon longer than the residues need and that codon is a
+    /// concrete stop in the table in use (`*` in a custom table); codons
+    /// with ambiguity codes never are.
+    fn split_terminal_stop(&mut self, nt_track_mode: NtTrackMode<'_>) {
+        let needed = self.residues().len() * 3;
+        if self.nucleotide.len() != needed + 3 {
+            return;
+        }
+        let codon = &self.nucleotide[needed..];
+        if codon_is_stop(nt_track_mode, codon.as_bytes()) {
+            self.metadata.terminal_stop = Some(codon.to_string());
+            self.metadata.stop_codons += 1;
+            self.nucleotide.truncate(needed);
+        }
+    }
+
+    /// Re-reads the nucleotide sequence as one codon per residue, allowing
+    /// frameshifts, when the strict walk would not explain every residue.
+    /// Missing bases become `!` and extra bases are dropped; each shift is
+    /// recorded. Nothing changes when the best alignment has no shift.
+    fn realign_frameshifts(
+        &mut self,
+        settings: FrameshiftAlignment,
+        nt_track_mode: NtTrackMode<'_>,
+    ) {
+        let residues = self.residues();
+        let perfect = residues.iter().filter(|aa| **aa != 'X').count();
+        let nt = self.nucleotide.as_bytes();
+        if nt.len() == residues.len() * 3
+            && Self::codon_score(&residues, nt, nt_track_mode) == perfect
+        {
+            return;
+        }
+        if !residues.iter().all(char::is_ascii) || !nt.is_ascii() {
+            return;
+        }
+
+        // Missing bases stand as `N`, so any codon they could complete counts.
+        let lenient = nt_track_mode.with_ambiguity(Ambiguity::Any);
+        let residue_bytes: Vec<u8> = residues.iter().map(|aa| *aa as u8).collect();
+        let realignment = frameshift::realign(&residue_bytes, nt, settings, |aa, codon| {
+            aa == b'X' || codon_matches_expected_in_mode(lenient, char::from(aa), codon)
+        });
+        let Some(realignment) = realignment.filter(|realignment| !realignment.shifts.is_empty())
+        else {
+            return;
+        };
+
+        let columns: Vec<usize> = self
+            .amino_acid
+            .bytes()
+            .enumerate()
+            .filter(|(_, c)| *c != b'-' && !c.is_ascii_digit())
+            .map(|(column, _)| column)
+            .collect();
+        self.metadata.frameshifts = realignment
+            .shifts
+            .into_iter()
+            .map(|(residue, nt_offset, kind)| Frameshift {
+                alignment_position: columns[residue] + 1,
+                nt_offset,
+                kind,
+            })
+            .collect();
+        self.nucleotide = realignment.nucleotide;
+    }
+
+    /// Runs the optional steps that decide which bases of the nucleotide
+    /// record the codon walk reads: flank trimming, frame detection,
+    /// terminal stop handling and frameshift realignment.
+    fn locate_codons(&mut self, nt_track_mode: NtTrackMode<'_>) {
+        let trimmed = self.options.trim_flanks && self.trim_flanks(nt_track_mode);
+        if self.options.detect_frame && !trimmed {
+            self.detect_frame(nt_track_mode);
+        }
+        if self.options.terminal_stop != TerminalStop::Reject {
+            self.split_terminal_stop(nt_track_mode);
+        }
+        if let Some(settings) = self.options.frameshifts {
+            self.realign_frameshifts(settings, nt_track_mode);
+        }
+    }
+
+    /// Completes the codon sequence produced by a walk.
+    fn finish(self, mut codon: String) -> (String, RecordMetadata) {
+        if self.options.terminal_stop == TerminalStop::Keep && !self.options.drop_terminal_stop {
+            if let Some(stop) = &self.metadata.terminal_stop {
+                codon.push_str(stop);
+            }
+        }
+        (codon, self.metadata)
+    }
+
+    /// Alignment index of the first residue that consumes a codon.
+    fn first_residue_index(&self) -> Option<usize> {
+        self.amino_acid
+            .chars()
+            .position(|c| c != '-' && !c.is_ascii_digit())
+    }
+
+    /// Alignment index of the residue whose codon ends the coding sequence;
+    /// `None` when a kept terminal stop is appended after it.
+    fn final_codon_index(&self) -> Option<usize> {
+        if self.options.terminal_stop == TerminalStop::Keep && self.metadata.terminal_stop.is_some()
+        {
+            return None;
+        }
+        self.amino_acid
+            .bytes()
+            .rposition(|c| c != b'-' && !c.is_ascii_digit())
+    }
+
+    /// What to write for a stop codon, which `is_final` when it ends the
+    /// coding sequence.
+    fn stop_codon_output<'c>(&self, codon: &'c str, is_final: bool) -> &'c str {
+        if is_final {
+            return if self.options.drop_terminal_stop {
+                "---"
+            } else {
+                codon
+            };
+        }
+        match self.options.internal_stops {
+            InternalStops::Keep => codon,
+            InternalStops::Mask => "NNN",
+            InternalStops::Gap => "---",
+        }
+    }
+
+    /// Whether `codon` may encode the `M` of the first residue as an
+    /// initiation codon. `table_starts` is the initiation-codon signature of
+    /// a built-in table; custom tables have none.
+    fn start_codon_match(
+        &self,
+        aa: char,
+        codon: &[u8],
+        table_starts: Option<&CompiledSignature>,
+    ) -> CodonMatch {
+        if aa != 'M' {
+            return CodonMatch::Mismatch;
+        }
+        let ambiguity = self.options.ambiguity;
+        match &self.options.start_codons {
+            StartCodons::Off => CodonMatch::Mismatch,
+            StartCodons::Table => table_starts.map_or(CodonMatch::Mismatch, |starts| {
+                starts.check('M', codon, ambiguity)
+            }),
+            StartCodons::Codons(codons) => {
+                let listed = |index: usize| {
+                    codons
+                        .iter()
+                        .any(|start| codon_index(start.as_bytes()) == Some(index))
+                };
+                if codons
+                    .iter()
+                    .any(|start| start.as_bytes().eq_ignore_ascii_case(codon))
+                {
+                    return CodonMatch::Exact;
+                }
+                if let Some(index) = codon_index(codon) {
+                    return if listed(index) {
+                        CodonMatch::Exact
+                    } else {
+                        CodonMatch::Mismatch
+                    };
+                }
+                let accepts = match ambiguity {
+                    Ambiguity::Any => std::str::from_utf8(codon).is_ok_and(|codon| {
+                        has_iupac_match(codon, codons.iter().map(String::as_str))
+                    }),
+                    Ambiguity::All => {
+                        let mut all = iupac_triplet_index(codon).is_some();
+                        for_each_expansion(codon, |index| all &= listed(index));
+                        all
+                    }
+                    Ambiguity::Reject => false,
+                };
+                if accepts {
+                    CodonMatch::Ambiguous
+                } else {
+                    CodonMatch::Mismatch
+                }
+            }
+        }
+    }
+
+    /// Records a problem with this record. Outside of collect-all mode only
+    /// the first problem is kept.
+    fn report_error(&self, error: Pn2CodonError) {
+        let mut errors = self.errors.borrow_mut();
+        if self.collect_all || errors.is_empty() {
+            errors.push(error);
+        }
+    }
+
+    fn has_errors(&self) -> bool {
+        !self.errors.borrow().is_empty()
+    }
+
+    fn take_errors(&self) -> Vec<Pn2CodonError> {
+        self.errors.take()
+    }
+
+    /// Describes the codon at `nt_base_index` that does not encode the
+    /// residue at `aa_index`, with the context shown for a mismatch.
+    fn describe_mismatch(
+        &self,
+        aa_index: usize,
+        nt_base_index: usize,
+        nt_track_mode: NtTrackMode<'_>,
+    ) -> MaskedCodon {
         let aa_seq: String = self
             .amino_acid
             .chars()
             .filter(|c| *c != '-' && !c.is_ascii_digit())
             .collect();
-        let nt_seq = self.nucleotide.clone();
         let aa_center = residue_index_for_alignment_position(&self.amino_acid, aa_index);
         let details = format_seq_inconsistency_details(
             self.aa_source_label.as_ref(),
@@ -719,21 +1163,64 @@ impl AminoAcidTranslator {
             &aa_seq,
             self.nt_source_label.as_ref(),
             &truncate_header(&self.nt_header),
-            &nt_seq,
+            &self.nucleotide,
             aa_center,
             nt_track_mode,
             Some(nt_base_index),
         );
-        self.report_error(format_error_block(
-            "Peptide and nucleotide sequences are inconsistent.",
-            &details,
-        ));
+        let nt_end = (nt_base_index + 3).min(self.nucleotide.len());
+        let codon = self
+            .nucleotide
+            .get(nt_base_index.min(nt_end)..nt_end)
+            .unwrap_or_default()
+            .to_string();
+        MaskedCodon {
+            alignment_position: aa_index + 1,
+            nt_offset: nt_base_index,
+            expected_aa: self.amino_acid.chars().nth(aa_index).unwrap_or('X'),
+            codon,
+            details,
+        }
+    }
+
+    fn error_out_mismatch(&self, aa_index: usize, nt_base_index: usize, nt_track_mode: NtTrackMode<'_>) {
+        let mismatch = self.describe_mismatch(aa_index, nt_base_index, nt_track_mode);
+        self.report_error(Pn2CodonError::CodonMismatch {
+            record: self.record_context(),
+            alignment_position: mismatch.alignment_position,
+            nt_offset: mismatch.nt_offset,
+            expected_aa: mismatch.expected_aa,
+            codon: mismatch.codon,
+            details: mismatch.details,
+        });
+    }
+
+    /// The mask for the next mismatching codon, while masking is enabled
+    /// and this record has not reached its limit.
+    fn mismatch_mask(&self) -> Option<&'a str> {
+        let options: &'a AlignOptions = self.options;
+        options
+            .mismatch_masking
+            .as_ref()
+            .filter(|masking| {
+                masking
+                    .limit
+                    .is_none_or(|limit| self.metadata.masked_codons.len() < limit)
+            })
+            .map(|masking| masking.mask.as_str())
     }
 
-    fn reverse_translate_and_compare_with_table(&self, gene_table: &GeneTable) -> String {
+    fn reverse_translate_and_compare_with_table(
+        &mut self,
+        gene_table: &CompiledGeneTable,
+    ) -> String {
         let mut compare_triplets = self.nucleotide.as_bytes().chunks(3);
         let mut nt_triplet_index = 0_usize;
         let mut output = String::with_capacity(self.nucleotide.len());
+        let first_residue = self.first_residue_index();
+        let final_codon = self.final_codon_index();
+        let mut missing_reported: Vec<char> = Vec::new();
+        let nt_track_mode = NtTrackMode::Table(gene_table, self.options.ambiguity);
 
         for (aa_index, aa) in self.amino_acid.chars().enumerate() {
             if aa == '-' {
@@ -748,29 +1235,25 @@ impl AminoAcidTranslator {
                 continue;
             }
 
-            let taxa = match gene_table.get(&aa) {
-                Some(codons) => codons,
-                None => {
-                    let mut supported: Vec<char> = gene_table.keys().copied().collect();
-                    supported.sort_unstable();
-                    let supported_list = supported
-                        .iter()
-                        .map(char::to_string)
-                        .collect::<Vec<String>>()
-                        .join(", ");
-                    let details = format!(
-                        "Amino acid         : '{}'\nAlignment position : {}\nValid symbols      : {}",
-                        aa,
-                        aa_index + 1,
-                        supported_list
-                    );
-                    self.report_error(format_error_block(
-                        "Amino acid is missing from custom codon table.",
-                        &details,
-                    ));
+            if !gene_table.has_symbol(aa) {
+                if !missing_reported.contains(&aa) {
+                    missing_reported.push(aa);
+                    self.report_error(Pn2CodonError::MissingAminoAcid {
+                        record: self.record_context(),
+                        amino_acid: aa,
+                        alignment_position: aa_index + 1,
+                        valid_symbols: gene_table.symbols().to_vec(),
+                    });
+                }
+                if !self.collect_all {
                     return String::new();
                 }
-            };
+                // Keep walking so later problems are still reported.
+                if compare_triplets.next().is_some() {
+                    nt_triplet_index += 1;
+                }
+                continue;
+            }
 
             let nt_base_index = nt_triplet_index * 3;
             let original_triplet = match compare_triplets.next() {
@@ -780,258 +1263,1374 @@ impl AminoAcidTranslator {
                         triplet
                     }
                     Err(_) => {
-                        self.error_out_mismatch(
-                            aa_index,
-                            nt_base_index,
-                            NtTrackMode::Table(gene_table),
-                        );
+                        self.error_out_mismatch(aa_index, nt_base_index, nt_track_mode);
                         return String::new();
                     }
                 },
                 _ => {
-                    self.error_out_mismatch(aa_index, nt_base_index, NtTrackMode::Table(gene_table));
+                    self.error_out_mismatch(aa_index, nt_base_index, nt_track_mode);
                     return String::new();
                 }
             };
 
-            if original_triplet.contains('N') || aa == 'X' {
-                output.push_str(original_triplet);
-                continue;
+            let triplet = original_triplet.as_bytes();
+            if !passes_unchecked(aa, triplet, self.options.ambiguity) {
+                let mut codon_match = gene_table.check(aa, triplet, self.options.ambiguity);
+                if !codon_match.is_match() && Some(aa_index) == first_residue {
+                    codon_match = self.start_codon_match(aa, triplet, None);
+                    if codon_match.is_match() {
+                        self.metadata.alternative_start = Some(original_triplet.to_string());
+                    }
+                }
+                match codon_match {
+                    CodonMatch::Exact => {}
+                    CodonMatch::Ambiguous => self.metadata.ambiguous_codons += 1,
+                    CodonMatch::Mismatch => {
+                        if let Some(mask) = self.mismatch_mask() {
+                            let masked =
+                                self.describe_mismatch(aa_index, nt_base_index, nt_track_mode);
+                            self.metadata.masked_codons.push(masked);
+                            output.push_str(mask);
+                            continue;
+                        }
+                        self.error_out_mismatch(aa_index, nt_base_index, nt_track_mode);
+                        if !self.collect_all {
+                            return String::new();
+                        }
+                    }
+                }
             }
 
-            if taxa.iter().any(|triplet| triplet == original_triplet)
-                || has_iupac_match(original_triplet, taxa.iter().map(String::as_str))
-            {
-                output.push_str(original_triplet);
+            if gene_table.is_stop(triplet) {
+                self.metadata.stop_codons += 1;
+                output.push_str(
+                    self.stop_codon_output(original_triplet, Some(aa_index) == final_codon),
+                );
             } else {
-                self.error_out_mismatch(aa_index, nt_base_index, NtTrackMode::Table(gene_table));
-                return String::new();
+                output.push_str(original_triplet);
             }
         }
 
         output
     }
 
-    fn reverse_translate_and_compare_with_signature(&self, signature: &str) -> String {
+    fn reverse_translate_and_compare_with_signature(
+        &mut self,
+        signature: &CompiledSignature,
+        starts: &CompiledSignature,
+    ) -> String {
+        // `streamline` leaves only ASCII residues, so the walk runs on bytes.
         let mut compare_triplets = self.nucleotide.as_bytes().chunks(3);
         let mut nt_triplet_index = 0_usize;
-        let mut output = String::with_capacity(self.nucleotide.len());
-
-        for (aa_index, aa) in self.amino_acid.chars().enumerate() {
-            if aa == '-' {
-                output.push_str("---");
-                continue;
-            }
-
-            if aa.is_ascii_digit() {
-                if let Some(digit) = aa.to_digit(10) {
-                    output.push_str(&".".repeat(digit as usize));
+        let mut output: Vec<u8> = Vec::with_capacity(self.nucleotide.len());
+        let first_residue = self.first_residue_index();
+        let final_codon = self.final_codon_index();
+        let ambiguity = self.options.ambiguity;
+        let nt_track_mode = NtTrackMode::Signature(signature, ambiguity);
+
+        for (aa_index, aa) in self.amino_acid.bytes().enumerate() {
+            match aa {
+                b'-' => {
+                    output.extend_from_slice(b"---");
+                    continue;
                 }
-                continue;
+                b'0'..=b'9' => {
+                    output.resize(output.len() + usize::from(aa - b'0'), b'.');
+                    continue;
+                }
+                _ => {}
             }
 
             let nt_base_index = nt_triplet_index * 3;
-            let original_triplet = match compare_triplets.next() {
-                Some(chunk) if chunk.len() == 3 => match std::str::from_utf8(chunk) {
-                    Ok(triplet) => {
-                        nt_triplet_index += 1;
-                        triplet
-                    }
-                    Err(_) => {
-                        self.error_out_mismatch(
-                            aa_index,
-                            nt_base_index,
-                            NtTrackMode::Signature(signature),
-                        );
-                        return String::new();
-                    }
-                },
+            let triplet = match compare_triplets.next() {
+                Some(chunk) if chunk.len() == 3 && chunk.is_ascii() => {
+                    nt_triplet_index += 1;
+                    chunk
+                }
                 _ => {
-                    self.error_out_mismatch(
-                        aa_index,
-                        nt_base_index,
-                        NtTrackMode::Signature(signature),
-                    );
+                    self.error_out_mismatch(aa_index, nt_base_index, nt_track_mode);
                     return String::new();
                 }
             };
 
-            if original_triplet.contains('N') || aa == 'X' {
-                output.push_str(original_triplet);
-                continue;
+            let aa = char::from(aa);
+            if !passes_unchecked(aa, triplet, ambiguity) {
+                let mut codon_match = signature.check(aa, triplet, ambiguity);
+                if !codon_match.is_match() && Some(aa_index) == first_residue {
+                    codon_match = self.start_codon_match(aa, triplet, Some(starts));
+                    if codon_match.is_match() {
+                        self.metadata.alternative_start =
+                            Some(String::from_utf8_lossy(triplet).into_owned());
+                    }
+                }
+                match codon_match {
+                    CodonMatch::Exact => {}
+                    CodonMatch::Ambiguous => self.metadata.ambiguous_codons += 1,
+                    CodonMatch::Mismatch => {
+                        if let Some(mask) = self.mismatch_mask() {
+                            let masked =
+                                self.describe_mismatch(aa_index, nt_base_index, nt_track_mode);
+                            self.metadata.masked_codons.push(masked);
+                            output.extend_from_slice(mask.as_bytes());
+                            continue;
+                        }
+                        self.error_out_mismatch(aa_index, nt_base_index, nt_track_mode);
+                        if !self.collect_all {
+                            return String::new();
+                        }
+                    }
+                }
             }
 
-            if codon_matches_signature(signature, aa, original_triplet)
-                || ambiguous_triplet_matches_signature(signature, aa, original_triplet)
-            {
-                output.push_str(original_triplet);
-            } else {
-                self.error_out_mismatch(
-                    aa_index,
-                    nt_base_index,
-                    NtTrackMode::Signature(signature),
+            if signature.is_stop(triplet) {
+                self.metadata.stop_codons += 1;
+                // ASCII was checked above.
+                let codon = std::str::from_utf8(triplet).unwrap_or_default();
+                output.extend_from_slice(
+                    self.stop_codon_output(codon, Some(aa_index) == final_codon)
+                        .as_bytes(),
                 );
-                return String::new();
+            } else {
+                output.extend_from_slice(triplet);
             }
         }
 
-        output
+        String::from_utf8(output).unwrap_or_default()
     }
 }
 
 fn translate_record_with_table(
-    gene_table: &GeneTable,
+    gene_table: &CompiledGeneTable,
     sequence_index: usize,
     aa_source_label: &Arc<str>,
     nt_source_label: &Arc<str>,
-    aa_header: String,
-    aa: String,
-    nt_header: String,
-    nt: String,
-) -> Result<String, String> {
+    record: PairedRecord,
+    options: &AlignOptions,
+    collect_all: bool,
+) -> Result<(String, RecordMetadata), Vec<Pn2CodonError>> {
     let mut translator = AminoAcidTranslator::new(
         sequence_index,
         Arc::clone(aa_source_label),
-        aa_header,
-        aa,
         Arc::clone(nt_source_label),
-        nt_header,
-        nt,
+        record,
+        options,
     );
+    translator.collect_all = collect_all;
     translator.streamline();
+    translator.locate_codons(NtTrackMode::Table(gene_table, options.ambiguity));
     translator.do_checks();
-    if translator.has_reported_error.get() {
-        return Err(translator.get_error_message().unwrap_or_else(|| {
-            format_error_block("Peptide and nucleotide sequences are inconsistent.", "")
-        }));
+    if translator.has_errors() && !translator.can_walk() {
+        return Err(translator.take_errors());
     }
     let codon = translator.reverse_translate_and_compare_with_table(gene_table);
-    if translator.has_reported_error.get() {
-        return Err(translator.get_error_message().unwrap_or_else(|| {
-            format_error_block("Peptide and nucleotide sequences are inconsistent.", "")
-        }));
+    if translator.has_errors() {
+        return Err(translator.take_errors());
     }
-    Ok(codon)
+    Ok(translator.finish(codon))
 }
 
+#[allow(clippy::too_many_arguments)]
 fn translate_record_with_signature(
-    signature: &str,
+    signature: &CompiledSignature,
+    starts: &CompiledSignature,
     sequence_index: usize,
     aa_source_label: &Arc<str>,
     nt_source_label: &Arc<str>,
-    aa_header: String,
-    aa: String,
-    nt_header: String,
-    nt: String,
-) -> Result<String, String> {
+    record: PairedRecord,
+    options: &AlignOptions,
+    collect_all: bool,
+) -> Result<(String, RecordMetadata), Vec<Pn2CodonError>> {
     let mut translator = AminoAcidTranslator::new(
         sequence_index,
         Arc::clone(aa_source_label),
-        aa_header,
-        aa,
         Arc::clone(nt_source_label),
-        nt_header,
-        nt,
+        record,
+        options,
     );
+    translator.collect_all = collect_all;
     translator.streamline();
+    translator.locate_codons(NtTrackMode::Signature(signature, options.ambiguity));
     translator.do_checks();
-    if translator.has_reported_error.get() {
-        return Err(translator.get_error_message().unwrap_or_else(|| {
-            format_error_block("Peptide and nucleotide sequences are inconsistent.", "")
-        }));
-    }
-    let codon = translator.reverse_translate_and_compare_with_signature(signature);
-    if translator.has_reported_error.get() {
-        return Err(translator.get_error_message().unwrap_or_else(|| {
-            format_error_block("Peptide and nucleotide sequences are inconsistent.", "")
-        }));
-    }
-    Ok(codon)
-}
-
-#[pyfunction]
-pub fn pn2codon(
-    _file_steem: String,
-    aa_path: String,
-    nt_path: String,
-    table_num: i32,
-    seqs: HashMap<String, ((String, String), (i32, String, String))>,
-) -> PyResult<String> {
-    let aa_source_label: Arc<str> = source_label_from_path(&aa_path, "aa.fa").into();
-    let nt_source_label: Arc<str> = source_label_from_path(&nt_path, "nt.fa").into();
-
-    let signature = match table_signature(table_num) {
-        Some(sig) => sig,
-        None => {
-            let details = format!(
-                "Requested table : {}\nSupported NCBI tables: {}",
-                table_num,
-                supported_table_numbers()
-            );
-            return Err(PyValueError::new_err(format_error_block(
-                "Invalid codon table number.",
-                &details,
-            )));
+    if translator.has_errors() && !translator.can_walk() {
+        return Err(translator.take_errors());
+    }
+    let codon = translator.reverse_translate_and_compare_with_signature(signature, starts);
+    if translator.has_errors() {
+        return Err(translator.take_errors());
+    }
+    Ok(translator.finish(codon))
+}
+
+/// One peptide/nucleotide pair to back-translate.
+///
+/// `header` is the line written in front of the codon alignment; the
+/// peptide and nucleotide headers are compared against each other.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PairedRecord {
+    pub header: String,
+    pub aa_header: String,
+    pub aa: String,
+    pub nt_header: String,
+    pub nt: String,
+}
+
+impl PairedRecord {
+    pub fn new(
+        header: impl Into<String>,
+        aa_header: impl Into<String>,
+        aa: impl Into<String>,
+        nt_header: impl Into<String>,
+        nt: impl Into<String>,
+    ) -> Self {
+        Self {
+            header: header.into(),
+            aa_header: aa_header.into(),
+            aa: aa.into(),
+            nt_header: nt_header.into(),
+            nt: nt.into(),
+        }
+    }
+}
+
+/// Genetic code used to check each codon against its aligned residue.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CodonTable {
+    /// An NCBI-style table, built in or loaded from a file, as its
+    /// 64-character amino-acid signature and matching initiation-codon
+    /// string.
+    Signature {
+        amino_acids: Cow<'static, str>,
+        starts: Cow<'static, str>,
+    },
+    /// A caller-supplied amino acid to codons mapping.
+    Custom(GeneTable),
+}
+
+impl CodonTable {
+    /// Looks up a built-in NCBI translation table by number.
+    pub fn from_table_number(table_num: i32) -> Result<Self, Pn2CodonError> {
+        genetic_code(table_num)
+            .map(Self::from_genetic_code)
+            .ok_or(Pn2CodonError::InvalidTable {
+                requested: table_num,
+            })
+    }
+
+    /// Looks up a built-in NCBI translation table by name, short name or
+    /// alias (see [`genetic_code_by_name`]).
+    pub fn from_table_name(name: &str) -> Result<Self, Pn2CodonError> {
+        genetic_code_by_name(name)
+            .map(Self::from_genetic_code)
+            .ok_or_else(|| Pn2CodonError::UnknownTableName {
+                requested: name.to_string(),
+            })
+    }
+
+    pub fn from_genetic_code(code: &GeneticCode) -> Self {
+        CodonTable::Signature {
+            amino_acids: Cow::Borrowed(code.amino_acids),
+            starts: Cow::Borrowed(code.starts),
+        }
+    }
+}
+
+/// Per-record behaviour switches shared by every translator of a run.
+#[derive(Clone, Debug, Default)]
+struct AlignOptions {
+    pairing: Pairing,
+    /// Worker threads for record translation; `None` uses one per core.
+    threads: Option<usize>,
+    /// The dedicated pool of `threads` workers, built once by
+    /// [`CodonAligner::with_threads`].
+    pool: Option<Arc<rayon::ThreadPool>>,
+    /// Search all six frames for the one matching the peptide.
+    detect_frame: bool,
+    /// Cut untranslated flanks off nucleotide records that are too long.
+    trim_flanks: bool,
+    terminal_stop: TerminalStop,
+    start_codons: StartCodons,
+    ambiguity: Ambiguity,
+    mismatch_masking: Option<MismatchMasking>,
+    internal_stops: InternalStops,
+    /// Write the stop codon ending the coding sequence as gaps, or leave
+    /// out a kept terminal stop.
+    drop_terminal_stop: bool,
+    frameshifts: Option<FrameshiftAlignment>,
+}
+
+/// Which codons may encode the `M` of a record's first residue besides
+/// those the table translates to `M`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum StartCodons {
+    #[default]
+    Off,
+    /// The NCBI initiation codons of the built-in table in use. Custom
+    /// tables have none, so this accepts nothing extra for them.
+    Table,
+    /// These codons, with any table.
+    Codons(Vec<String>),
+}
+
+/// What to do with a stop codon that ends the nucleotide record but has no
+/// `*` in the peptide.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TerminalStop {
+    /// Treat it like any other extra bases: a length mismatch.
+    #[default]
+    Reject,
+    /// Accept it and leave it out of the codon alignment.
+    Strip,
+    /// Accept it and append it to the codon sequence.
+    Keep,
+}
+
+/// When a codon with IUPAC ambiguity codes, such as `RAY`, counts as
+/// encoding the residue it is aligned to. Under [`Ambiguity::Any`], codons
+/// containing `N` are passed through unchecked and not counted as
+/// ambiguous; the other policies check them like any other codon.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Ambiguity {
+    /// Accept it if any of its expansions encodes the residue.
+    #[default]
+    Any,
+    /// Accept it only if every expansion encodes the residue.
+    All,
+    /// Treat it as a mismatch.
+    Reject,
+}
+
+/// How stop codons before the end of the coding sequence are written.
+/// Codon-model tools such as PAML and HyPhy reject alignments with them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InternalStops {
+    /// Copy them through.
+    #[default]
+    Keep,
+    /// Write `NNN`.
+    Mask,
+    /// Write `---`.
+    Gap,
+}
+
+/// Replaces codons that do not encode their aligned residue with a mask
+/// instead of failing the record.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MismatchMasking {
+    /// Written in place of each mismatching codon; always three characters,
+    /// which keeps the codon alignment in frame.
+    mask: String,
+    /// Most codons masked in one record; a further mismatch fails the
+    /// record as usual. `None` masks every mismatch.
+    pub limit: Option<usize>,
+}
+
+impl Default for MismatchMasking {
+    fn default() -> Self {
+        Self {
+            mask: "NNN".to_string(),
+            limit: None,
+        }
+    }
+}
+
+impl MismatchMasking {
+    /// Masking with `mask` instead of `NNN`, or `None` when `mask` is not
+    /// three characters.
+    pub fn with_mask(mask: &str) -> Option<Self> {
+        (mask.chars().count() == 3).then(|| Self {
+            mask: mask.to_string(),
+            limit: None,
+        })
+    }
+
+    /// What is written in place of each mismatching codon.
+    pub fn mask(&self) -> &str {
+        &self.mask
+    }
+}
+
+impl AlignOptions {
+    /// Applies `f` to every item with its 0-based index across the
+    /// configured worker threads, returning the results in input order.
+    ///
+    /// Runs serially with `threads == Some(1)` or for a single item.
+    fn par_map<T, R, F>(&self, items: Vec<T>, f: F) -> Vec<R>
+    where
+        T: Send,
+        R: Send,
+        F: Fn(usize, T) -> R + Sync + Send,
+    {
+        let serial = |items: Vec<T>| {
+            items
+                .into_iter()
+                .enumerate()
+                .map(|(i, t)| f(i, t))
+                .collect()
+        };
+        if items.len() <= 1 || self.threads == Some(1) {
+            return serial(items);
+        }
+        let parallel = |items: Vec<T>| {
+            items
+                .into_par_iter()
+                .enumerate()
+                .map(|(i, t)| f(i, t))
+                .collect()
+        };
+        match &self.pool {
+            Some(pool) => pool.install(|| parallel(items)),
+            None => parallel(items),
+        }
+    }
+}
+
+/// A [`CodonTable`] compiled once per aligner for the codon walk.
+#[derive(Clone, Debug)]
+enum CompiledTable {
+    Signature {
+        amino_acids: Arc<CompiledSignature>,
+        starts: Arc<CompiledSignature>,
+    },
+    Custom(Arc<CompiledGeneTable>),
+}
+
+impl CompiledTable {
+    fn new(table: &CodonTable) -> Self {
+        match table {
+            CodonTable::Signature {
+                amino_acids,
+                starts,
+            } => CompiledTable::Signature {
+                amino_acids: Arc::new(CompiledSignature::new(amino_acids)),
+                starts: Arc::new(CompiledSignature::new(starts)),
+            },
+            CodonTable::Custom(gene_table) => {
+                CompiledTable::Custom(Arc::new(CompiledGeneTable::new(gene_table)))
+            }
+        }
+    }
+}
+
+/// Back-translates aligned peptides into codon alignments.
+///
+/// This is the entry point for Rust callers; the Python bindings are a thin
+/// layer over it.
+#[derive(Clone, Debug)]
+pub struct CodonAligner {
+    table: CodonTable,
+    compiled: CompiledTable,
+    aa_source_label: Arc<str>,
+    nt_source_label: Arc<str>,
+    options: AlignOptions,
+}
+
+impl CodonAligner {
+    pub fn new(table: CodonTable) -> Self {
+        Self {
+            compiled: CompiledTable::new(&table),
+            table,
+            aa_source_label: source_label_from_path("", "aa.fa").into(),
+            nt_source_label: source_label_from_path("", "nt.fa").into(),
+            options: AlignOptions::default(),
         }
+    }
+
+    pub fn from_table_number(table_num: i32) -> Result<Self, Pn2CodonError> {
+        CodonTable::from_table_number(table_num).map(Self::new)
+    }
+
+    pub fn from_table_name(name: &str) -> Result<Self, Pn2CodonError> {
+        CodonTable::from_table_name(name).map(Self::new)
+    }
+
+    /// Validates a custom codon table with [`CodonTable::from_gene_table`]
+    /// and aligns with its normalized form.
+    pub fn from_gene_table(gene_table: &GeneTable, source: &str) -> Result<Self, Pn2CodonError> {
+        CodonTable::from_gene_table(gene_table, source).map(Self::new)
+    }
+
+    /// Sets the file paths used to label peptide and nucleotide sources in
+    /// error messages. Empty paths fall back to `aa.fa` and `nt.fa`.
+    pub fn with_source_paths(mut self, aa_path: &str, nt_path: &str) -> Self {
+        self.aa_source_label = source_label_from_path(aa_path, "aa.fa").into();
+        self.nt_source_label = source_label_from_path(nt_path, "nt.fa").into();
+        self
+    }
+
+    /// Sets how file records are paired in [`CodonAligner::align_files`].
+    ///
+    /// With [`Pairing::ById`], peptide and nucleotide headers are also
+    /// compared by extracted ID rather than verbatim.
+    pub fn with_pairing(mut self, pairing: Pairing) -> Self {
+        self.options.pairing = pairing;
+        self
+    }
+
+    /// Enables frame detection: each nucleotide record is read in whichever
+    /// of its six frames (three per strand) best explains the peptide, and
+    /// the chosen frame is recorded in [`RecordMetadata::frame`].
+    pub fn with_frame_detection(mut self, detect_frame: bool) -> Self {
+        self.options.detect_frame = detect_frame;
+        self
+    }
+
+    /// Enables flank trimming: a nucleotide record longer than three bases
+    /// per residue is cut down to the stretch whose codons best match the
+    /// peptide, so transcripts with UTRs or adapter leftovers still align.
+    /// The kept stretch is recorded in [`RecordMetadata::coding_region`].
+    pub fn with_flank_trimming(mut self, trim_flanks: bool) -> Self {
+        self.options.trim_flanks = trim_flanks;
+        self
+    }
+
+    /// Sets how an unrepresented terminal stop codon is handled. An accepted
+    /// stop is recorded in [`RecordMetadata::terminal_stop`]. With flank
+    /// trimming, a stop after the coding region is trimmed as part of the
+    /// 3' flank instead.
+    pub fn with_terminal_stop(mut self, terminal_stop: TerminalStop) -> Self {
+        self.options.terminal_stop = terminal_stop;
+        self
+    }
+
+    /// Sets which alternative initiation codons (such as `GTG` or `TTG`) are
+    /// accepted under a leading `M`. An accepted codon is recorded in
+    /// [`RecordMetadata::alternative_start`].
+    pub fn with_start_codons(mut self, start_codons: StartCodons) -> Self {
+        self.options.start_codons = start_codons;
+        self
+    }
+
+    /// Sets when codons with IUPAC ambiguity codes are accepted. Codons
+    /// accepted only through their ambiguity codes are counted in
+    /// [`RecordMetadata::ambiguous_codons`].
+    pub fn with_ambiguity(mut self, ambiguity: Ambiguity) -> Self {
+        self.options.ambiguity = ambiguity;
+        self
+    }
+
+    /// Masks mismatching codons instead of failing their record, or stops
+    /// masking with `None` (the default). Each masked codon is recorded in
+    /// [`RecordMetadata::masked_codons`].
+    pub fn with_mismatch_masking(mut self, masking: Option<MismatchMasking>) -> Self {
+        self.options.mismatch_masking = masking;
+        self
+    }
+
+    /// Sets how stop codons inside the coding sequence are written. Stops
+    /// are found with the table in use and counted in
+    /// [`RecordMetadata::stop_codons`].
+    pub fn with_internal_stops(mut self, internal_stops: InternalStops) -> Self {
+        self.options.internal_stops = internal_stops;
+        self
+    }
+
+    /// Writes a stop codon that ends the coding sequence as gaps, so the
+    /// alignment keeps its width, and leaves out a terminal stop kept with
+    /// [`TerminalStop::Keep`].
+    pub fn with_terminal_stop_dropped(mut self, drop: bool) -> Self {
+        self.options.drop_terminal_stop = drop;
+        self
+    }
+
+    /// Aligns records whose nucleotide sequence does not hold exactly one
+    /// matching codon per residue again, allowing frameshifts (`None`, the
+    /// default, keeps the strict walk). Bases missing from a codon are
+    /// written as `!`, extra bases are left out, and every shift is listed
+    /// in [`RecordMetadata::frameshifts`]. Records too far out of step for
+    /// the alignment's memory cap are left to the strict walk.
+    pub fn with_frameshifts(mut self, frameshifts: Option<FrameshiftAlignment>) -> Self {
+        self.options.frameshifts = frameshifts;
+        self
+    }
+
+    /// Sets how many worker threads translate records. `None` (the default)
+    /// uses one per core, or the enclosing pool when called from a rayon
+    /// worker; `Some(1)` translates on the calling thread.
+    ///
+    /// Any other count starts a dedicated pool here, shared by every run of
+    /// the aligner, and fails with [`Pn2CodonError::ThreadPool`] if it
+    /// cannot be started.
+    pub fn with_threads(mut self, threads: Option<usize>) -> Result<Self, Pn2CodonError> {
+        self.options.pool = match threads {
+            None | Some(1) => None,
+            Some(threads) => {
+                let pool = rayon::ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .build()
+                    .map_err(|err| Pn2CodonError::ThreadPool {
+                        message: err.to_string(),
+                    })?;
+                Some(Arc::new(pool))
+            }
+        };
+        self.options.threads = threads;
+        Ok(self)
+    }
+
+    pub fn table(&self) -> &CodonTable {
+        &self.table
+    }
+
+    /// Back-translates a single record, returning its codon sequence.
+    ///
+    /// `sequence_index` is the 1-based position reported in error messages.
+    pub fn translate_record(
+        &self,
+        sequence_index: usize,
+        record: PairedRecord,
+    ) -> Result<String, Pn2CodonError> {
+        self.translate_record_inner(sequence_index, record, false)
+            .map(|(codon, _)| codon)
+            .map_err(|mut errors| errors.swap_remove(0))
+    }
+
+    /// Back-translates a single record, returning every problem found in it
+    /// rather than only the first.
+    pub fn check_record(
+        &self,
+        sequence_index: usize,
+        record: PairedRecord,
+    ) -> Result<String, Vec<Pn2CodonError>> {
+        self.translate_record_inner(sequence_index, record, true)
+            .map(|(codon, _)| codon)
+    }
+
+    /// Like [`CodonAligner::check_record`], but keeps the record's header
+    /// and [`RecordMetadata`].
+    pub fn check_codon_record(
+        &self,
+        sequence_index: usize,
+        mut record: PairedRecord,
+    ) -> Result<CodonRecord, RecordDiagnostics> {
+        let header = std::mem::take(&mut record.header);
+        match self.translate_record_inner(sequence_index, record, true) {
+            Ok((sequence, metadata)) => Ok(CodonRecord {
+                header,
+                sequence,
+                metadata,
+            }),
+            Err(errors) => Err(RecordDiagnostics {
+                sequence_index,
+                header,
+                errors,
+            }),
+        }
+    }
+
+    fn translate_record_inner(
+        &self,
+        sequence_index: usize,
+        record: PairedRecord,
+        collect_all: bool,
+    ) -> Result<(String, RecordMetadata), Vec<Pn2CodonError>> {
+        match &self.compiled {
+            CompiledTable::Signature {
+                amino_acids,
+                starts,
+            } => translate_record_with_signature(
+                amino_acids,
+                starts,
+                sequence_index,
+                &self.aa_source_label,
+                &self.nt_source_label,
+                record,
+                &self.options,
+                collect_all,
+            ),
+            CompiledTable::Custom(gene_table) => translate_record_with_table(
+                gene_table,
+                sequence_index,
+                &self.aa_source_label,
+                &self.nt_source_label,
+                record,
+                &self.options,
+                collect_all,
+            ),
+        }
+    }
+
+    /// Back-translates every record and renders the codon alignment as
+    /// FASTA text, failing with the error of the first record that fails.
+    pub fn align<I>(&self, records: I) -> Result<String, Pn2CodonError>
+    where
+        I: IntoIterator<Item = PairedRecord>,
+    {
+        let results = self.options.par_map(
+            records.into_iter().collect(),
+            |index, mut record: PairedRecord| {
+                let header = std::mem::take(&mut record.header);
+                self.translate_record(index + 1, record)
+                    .map(|codon| (header, codon))
+            },
+        );
+        let mut file = String::new();
+        for result in results {
+            let (header, codon) = result?;
+            file.push_str(&header);
+            file.push('\n');
+            file.push_str(&codon);
+            file.push('\n');
+        }
+        Ok(file)
+    }
+
+    /// Reads a peptide and a nucleotide FASTA file, pairs their records
+    /// according to [`CodonAligner::with_pairing`] and back-translates them,
+    /// labelling errors with the paths.
+    pub fn align_files<P, Q>(&self, aa_path: P, nt_path: Q) -> Result<String, Pn2CodonError>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let records = self.read_records(&aa_path, &nt_path)?;
+        self.clone()
+            .with_source_paths(
+                &aa_path.as_ref().to_string_lossy(),
+                &nt_path.as_ref().to_string_lossy(),
+            )
+            .align(records)
+    }
+
+    /// Reads a peptide and a nucleotide FASTA file and pairs their records
+    /// according to [`CodonAligner::with_pairing`], without translating.
+    pub fn read_records<P, Q>(
+        &self,
+        aa_path: P,
+        nt_path: Q,
+    ) -> Result<Vec<PairedRecord>, Pn2CodonError>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        match &self.options.pairing {
+            Pairing::Position => read_paired_records(&aa_path, &nt_path),
+            Pairing::ById(id_extractor) => pair_records_by_id(
+                read_fasta(&aa_path)?,
+                read_fasta(&nt_path)?,
+                id_extractor,
+                &source_label_from_path(&aa_path.as_ref().to_string_lossy(), "aa.fa"),
+                &source_label_from_path(&nt_path.as_ref().to_string_lossy(), "nt.fa"),
+            ),
+        }
+    }
+
+    /// Like [`CodonAligner::align_files`], but writes the codon alignment to
+    /// `out_path` instead of returning it.
+    pub fn align_files_to<P, Q, R>(
+        &self,
+        aa_path: P,
+        nt_path: Q,
+        out_path: R,
+        compression: Compression,
+    ) -> Result<(), Pn2CodonError>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+        R: AsRef<Path>,
+    {
+        let file = self.align_files(aa_path, nt_path)?;
+        write_output(out_path, &file, compression)
+    }
+
+    /// Back-translates records lazily, one per [`Iterator::next`] call, so
+    /// the codon alignment never has to be held in memory as a whole.
+    pub fn stream<I>(self, records: I) -> CodonStream<I::IntoIter>
+    where
+        I: IntoIterator<Item = PairedRecord>,
+    {
+        CodonStream {
+            aligner: self,
+            records: records.into_iter(),
+            sequence_index: 0,
+        }
+    }
+
+    /// Reads and pairs a peptide and a nucleotide FASTA file like
+    /// [`CodonAligner::align_files`], then streams the back-translation.
+    ///
+    /// Only the output is streamed: both files are read and paired in full
+    /// before the first record is yielded, so memory still grows with the
+    /// size of the inputs.
+    pub fn stream_files<P, Q>(
+        &self,
+        aa_path: P,
+        nt_path: Q,
+    ) -> Result<CodonStream<std::vec::IntoIter<PairedRecord>>, Pn2CodonError>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let records = self.read_records(&aa_path, &nt_path)?;
+        Ok(self
+            .clone()
+            .with_source_paths(
+                &aa_path.as_ref().to_string_lossy(),
+                &nt_path.as_ref().to_string_lossy(),
+            )
+            .stream(records))
+    }
+
+    /// Back-translates every record, keeping going past failures.
+    ///
+    /// Successful records are returned in input order alongside one
+    /// [`RecordDiagnostics`] per failed record.
+    pub fn align_all<I>(&self, records: I) -> AlignmentReport
+    where
+        I: IntoIterator<Item = PairedRecord>,
+    {
+        let results = self.options.par_map(
+            records.into_iter().collect(),
+            |index, record: PairedRecord| self.check_codon_record(index + 1, record),
+        );
+        let mut report = AlignmentReport::default();
+        for result in results {
+            match result {
+                Ok(record) => report.records.push(record),
+                Err(failure) => report.failures.push(failure),
+            }
+        }
+        report
+    }
+}
+
+/// A back-translated record.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CodonRecord {
+    pub header: String,
+    pub sequence: String,
+    pub metadata: RecordMetadata,
+}
+
+/// Strand of the nucleotide record that codons were read from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Strand {
+    Forward,
+    Reverse,
+}
+
+/// A reading frame: the strand, and how many leading bases of it were
+/// skipped before the first codon.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Frame {
+    pub strand: Strand,
+    /// 0, 1 or 2.
+    pub offset: usize,
+}
+
+impl Frame {
+    /// The record as given, read from its first base.
+    pub const FORWARD: Frame = Frame {
+        strand: Strand::Forward,
+        offset: 0,
     };
+}
 
-    let mut file = String::new();
-    for (index, (header, ((aa_header, aa), (_, nt_header, nt)))) in seqs.into_iter().enumerate() {
-        let codon = translate_record_with_signature(
-            signature,
-            index + 1,
-            &aa_source_label,
-            &nt_source_label,
-            aa_header,
-            aa,
-            nt_header,
-            nt,
-        )
-        .map_err(PyValueError::new_err)?;
-        file.push_str(&header);
-        file.push('\n');
-        file.push_str(&codon);
-        file.push('\n');
-    }
-    Ok(file)
-}
-
-#[pyfunction]
-pub fn pn2codon_original_args(
-    _file_steem: String,
-    aa_path: String,
-    nt_path: String,
-    gene_table: HashMap<char, Vec<String>>,
-    seqs: HashMap<String, ((String, String), (String, String))>,
-) -> PyResult<String> {
-    let aa_source_label: Arc<str> = source_label_from_path(&aa_path, "aa.fa").into();
-    let nt_source_label: Arc<str> = source_label_from_path(&nt_path, "nt.fa").into();
-
-    let mut file = String::new();
-    for (index, (header, ((aa_header, aa), (nt_header, nt)))) in seqs.into_iter().enumerate() {
-        let codon = translate_record_with_table(
-            &gene_table,
-            index + 1,
-            &aa_source_label,
-            &nt_source_label,
-            aa_header,
-            aa,
-            nt_header,
-            nt,
-        )
-        .map_err(PyValueError::new_err)?;
-        file.push_str(&header);
-        file.push('\n');
-        file.push_str(&codon);
-        file.push('\n');
-    }
-    Ok(file)
-}
-
-#[pymodule]
-fn pr2codon(_py: Python, m: &PyModule) -> PyResult<()> {
-    m.add_function(wrap_pyfunction!(pn2codon, m)?)?;
-    m.add_function(wrap_pyfunction!(pn2codon_original_args, m)?)?;
-    m.add_function(wrap_pyfunction!(attempt_iupac_substitution, m)?)?;
-    Ok(())
+/// Renders the conventional frame name: `+1` to `+3`, `-1` to `-3`.
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = match self.strand {
+            Strand::Forward => '+',
+            Strand::Reverse => '-',
+        };
+        write!(f, "{}{}", sign, self.offset + 1)
+    }
+}
+
+/// What back-translation found out about a record besides its codons.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RecordMetadata {
+    /// The frame codons were read in, when frame detection is enabled.
+    pub frame: Option<Frame>,
+    /// 0-based, end-exclusive range of the nucleotide record (as given,
+    /// after gap removal) that was kept when flank trimming cut it down.
+    pub coding_region: Option<std::ops::Range<usize>>,
+    /// The stop codon that ended the nucleotide record without a `*` in the
+    /// peptide, when terminal stops are accepted.
+    pub terminal_stop: Option<String>,
+    /// The codon accepted as an initiator under the first residue's `M`
+    /// although the table translates it otherwise.
+    pub alternative_start: Option<String>,
+    /// How many codons were accepted only through IUPAC ambiguity codes,
+    /// under the [`Ambiguity`] policy in use.
+    pub ambiguous_codons: usize,
+    /// Mismatching codons replaced by the mask, with mismatch masking.
+    pub masked_codons: Vec<MaskedCodon>,
+    /// How many stop codons the record has, a terminal stop missing from
+    /// the peptide included.
+    pub stop_codons: usize,
+    /// Frameshifts placed by frameshift-tolerant alignment, in order.
+    pub frameshifts: Vec<Frameshift>,
+}
+
+/// A codon that did not encode its aligned residue and was masked.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MaskedCodon {
+    /// 1-based column in the aligned peptide.
+    pub alignment_position: usize,
+    /// 0-based offset of the codon in the cleaned nucleotide sequence.
+    pub nt_offset: usize,
+    pub expected_aa: char,
+    /// The codon as it was before masking.
+    pub codon: String,
+    /// The context window a codon mismatch error shows.
+    pub details: String,
+}
+
+impl fmt::Display for MaskedCodon {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let details = format!(
+            "Alignment position : {}\nCodon              : {} (expected '{}')\n\n{}",
+            self.alignment_position, self.codon, self.expected_aa, self.details
+        );
+        f.write_str(&format_block(
+            "WARNING",
+            "Mismatching codon masked.",
+            &details,
+        ))
+    }
+}
+
+/// Iterator returned by [`CodonAligner::stream`].
+///
+/// Yields one result per input record, in input order. A failed record
+/// does not end the stream. Records are translated as the iterator
+/// advances, but it holds every input record it has not yet yielded.
+#[derive(Clone, Debug)]
+pub struct CodonStream<I> {
+    aligner: CodonAligner,
+    records: I,
+    /// 1-based index of the last record taken from `records`.
+    sequence_index: usize,
+}
+
+impl<I> Iterator for CodonStream<I>
+where
+    I: Iterator<Item = PairedRecord>,
+{
+    type Item = Result<CodonRecord, RecordDiagnostics>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let record = self.records.next()?;
+        self.sequence_index += 1;
+        Some(self.aligner.check_codon_record(self.sequence_index, record))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.records.size_hint()
+    }
+}
+
+impl<I> CodonStream<I>
+where
+    I: Iterator<Item = PairedRecord>,
+{
+    /// Writes the remaining records to `writer` as FASTA as they are
+    /// translated, stopping at the first record that fails.
+    ///
+    /// Returns the number of records written. Records written before a
+    /// failure stay written.
+    pub fn write_to<W: Write>(&mut self, mut writer: W) -> Result<usize, Pn2CodonError> {
+        let write_error = |err: std::io::Error| Pn2CodonError::Write {
+            path: "<writer>".to_string(),
+            message: err.to_string(),
+        };
+        let mut written = 0;
+        for result in self.by_ref() {
+            let record = result.map_err(|mut failure| failure.errors.swap_remove(0))?;
+            writeln!(writer, "{}\n{}", record.header, record.sequence).map_err(write_error)?;
+            written += 1;
+        }
+        writer.flush().map_err(write_error)?;
+        Ok(written)
+    }
+}
+
+/// Every problem found in one failed record.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecordDiagnostics {
+    /// 1-based position of the record in the input.
+    pub sequence_index: usize,
+    pub header: String,
+    pub errors: Vec<Pn2CodonError>,
+}
+
+/// Result of [`CodonAligner::align_all`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AlignmentReport {
+    pub records: Vec<CodonRecord>,
+    pub failures: Vec<RecordDiagnostics>,
+}
+
+impl AlignmentReport {
+    pub fn is_success(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// Renders the successful records as FASTA text.
+    pub fn to_fasta(&self) -> String {
+        let mut file = String::new();
+        for record in &self.records {
+            file.push_str(&record.header);
+            file.push('\n');
+            file.push_str(&record.sequence);
+            file.push('\n');
+        }
+        file
+    }
+
+    /// Renders per-record metadata of the successful records as TSV with
+    /// columns `header` (without `>`), `frame`, `coding_start` and
+    /// `coding_end` (0-based, end-exclusive), `terminal_stop`,
+    /// `alternative_start`, `ambiguous_codons`, `masked_positions`
+    /// (comma-separated 1-based alignment columns), `stop_codons` and
+    /// `frameshifts` (comma-separated `column:shift`, such as `12:-1`).
+    /// Empty cells mean the value was not computed.
+    pub fn metadata_tsv(&self) -> String {
+        let mut out = String::from(concat!(
+            "header\tframe\tcoding_start\tcoding_end\tterminal_stop\t",
+            "alternative_start\tambiguous_codons\tmasked_positions\tstop_codons\t",
+            "frameshifts\n",
+        ));
+        for record in &self.records {
+            let metadata = &record.metadata;
+            let frame = metadata
+                .frame
+                .map(|frame| frame.to_string())
+                .unwrap_or_default();
+            let (coding_start, coding_end) = match &metadata.coding_region {
+                Some(region) => (region.start.to_string(), region.end.to_string()),
+                None => (String::new(), String::new()),
+            };
+            out.push_str(&format!(
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+                record.header.trim_start_matches('>'),
+                frame,
+                coding_start,
+                coding_end,
+                metadata.terminal_stop.as_deref().unwrap_or_default(),
+                metadata.alternative_start.as_deref().unwrap_or_default(),
+                metadata.ambiguous_codons,
+                metadata
+                    .masked_codons
+                    .iter()
+                    .map(|masked| masked.alignment_position.to_string())
+                    .collect::<Vec<String>>()
+                    .join(","),
+                metadata.stop_codons,
+                metadata
+                    .frameshifts
+                    .iter()
+                    .map(|shift| shift.to_string())
+                    .collect::<Vec<String>>()
+                    .join(",")
+            ));
+        }
+        out
+    }
+
+    /// Iterates over every error of every failed record, in input order.
+    pub fn errors(&self) -> impl Iterator<Item = &Pn2CodonError> {
+        self.failures
+            .iter()
+            .flat_map(|failure| failure.errors.iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Table 1 as a custom gene table, so both walkers can be checked.
+    fn standard_gene_table() -> GeneTable {
+        let code = genetic_code(1).unwrap();
+        let mut gene_table = GeneTable::new();
+        for (index, symbol) in code.amino_acids.chars().enumerate() {
+            let codon: String = [index / 16, index / 4 % 4, index % 4]
+                .iter()
+                .map(|rank| ['T', 'C', 'A', 'G'][*rank])
+                .collect();
+            gene_table.entry(symbol).or_default().push(codon);
+        }
+        gene_table.insert('X', vec!["NNN".to_string()]);
+        gene_table
+    }
+
+    fn aligners() -> [CodonAligner; 2] {
+        [
+            CodonAligner::from_table_number(1).unwrap(),
+            CodonAligner::from_gene_table(&standard_gene_table(), "<test>").unwrap(),
+        ]
+    }
+
+    fn align(aligner: &CodonAligner, aa: &str, nt: &str) -> CodonRecord {
+        aligner
+            .check_codon_record(1, PairedRecord::new(">a", ">a", aa, ">a", nt))
+            .unwrap()
+    }
+
+    #[test]
+    fn ambiguous_codon_accepted_for_residue_is_not_a_stop() {
+        // YAR expands to TAA and TAG as well as CAA and CAG (Q).
+        for aligner in aligners() {
+            let aligner = aligner.with_internal_stops(InternalStops::Gap);
+            let record = align(&aligner, "MQK", "ATGYARAAA");
+            assert_eq!(record.sequence, "ATGYARAAA");
+            assert_eq!(record.metadata.stop_codons, 0);
+        }
+    }
+
+    #[test]
+    fn unknown_codon_is_not_a_stop() {
+        for aligner in aligners() {
+            let aligner = aligner.with_internal_stops(InternalStops::Gap);
+            let record = align(&aligner, "MXK", "ATGNNNAAA");
+            assert_eq!(record.sequence, "ATGNNNAAA");
+            assert_eq!(record.metadata.stop_codons, 0);
+
+            let aligner = aligner.with_terminal_stop_dropped(true);
+            let record = align(&aligner, "MKX", "ATGAAANNN");
+            assert_eq!(record.sequence, "ATGAAANNN");
+            assert_eq!(record.metadata.stop_codons, 0);
+        }
+    }
+
+    #[test]
+    fn validated_custom_table_keeps_iupac_codons() {
+        let table = CodonTable::from_gene_table(&standard_gene_table(), "<test>").unwrap();
+        match &table {
+            CodonTable::Custom(gene_table) => assert_eq!(gene_table[&'X'], ["NNN"]),
+            _ => panic!("expected a custom table"),
+        }
+        let record = align(&CodonAligner::new(table), "MX-K", "ATGNNNAAA");
+        assert_eq!(record.sequence, "ATGNNN---AAA");
+    }
+
+    #[test]
+    fn thread_counts_give_the_same_alignment() {
+        let records = || {
+            (0..8).map(|i| {
+                let header = format!(">r{}", i);
+                PairedRecord::new(&header, &header, "MK-", &header, "ATGAAA")
+            })
+        };
+        let serial = CodonAligner::from_table_number(1)
+            .unwrap()
+            .with_threads(Some(1))
+            .unwrap();
+        let expected = serial.align(records()).unwrap();
+        let pooled = serial.with_threads(Some(3)).unwrap();
+        for _ in 0..2 {
+            assert_eq!(pooled.align(records()).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn ambiguity_all_needs_every_expansion_to_encode_the_residue() {
+        for aligner in aligners() {
+            let aligner = aligner.with_ambiguity(Ambiguity::All);
+            let record = align(&aligner, "MA", "ATGGCN");
+            assert_eq!(record.metadata.ambiguous_codons, 1);
+
+            // YTN expands to CTN (L) but also TTT and TTC (F).
+            let result =
+                aligner.check_codon_record(1, PairedRecord::new(">a", ">a", "ML", ">a", "ATGYTN"));
+            assert!(result.is_err());
+            let any = aligner.with_ambiguity(Ambiguity::Any);
+            assert_eq!(align(&any, "ML", "ATGYTN").sequence, "ATGYTN");
+        }
+    }
+
+    #[test]
+    fn ambiguity_reject_fails_on_any_iupac_codon() {
+        for aligner in aligners() {
+            let aligner = aligner.with_ambiguity(Ambiguity::Reject);
+            for nt in ["ATGGCN", "ATGGCR", "ATGYTR"] {
+                let result =
+                    aligner.check_codon_record(1, PairedRecord::new(">a", ">a", "MA", ">a", nt));
+                assert!(result.is_err(), "{}", nt);
+            }
+            assert_eq!(align(&aligner, "MA", "ATGGCT").metadata.ambiguous_codons, 0);
+        }
+    }
+
+    #[test]
+    fn ambiguous_codons_are_counted() {
+        for aligner in aligners() {
+            // Under `Any`, the `N` codon is passed through uncounted.
+            for (ambiguity, expected) in [(Ambiguity::Any, 2), (Ambiguity::All, 3)] {
+                let aligner = aligner.clone().with_ambiguity(ambiguity);
+                let record = align(&aligner, "MA-LKK", "ATGGCNYTRAAAAAR");
+                assert_eq!(record.sequence, "ATGGCN---YTRAAAAAR");
+                assert_eq!(record.metadata.ambiguous_codons, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn signature_masks_agree_with_explicit_expansion() {
+        let residues = "ABCDEFGHIJKLMNPQRSTVWYZ*";
+        for code in GENETIC_CODES {
+            let signature = CompiledSignature::new(code.amino_acids);
+            let codons_of = |aa: char| -> Vec<String> {
+                let accepted = residue_mask(aa);
+                code.amino_acids
+                    .bytes()
+                    .enumerate()
+                    .filter(|(_, symbol)| symbol_bit(*symbol) & accepted != 0)
+                    .map(|(index, _)| tables::codon_at(index))
+                    .collect()
+            };
+            for index in 0..IUPAC_BASES.len().pow(3) {
+                let triplet = [
+                    IUPAC_BASES[index / 225],
+                    IUPAC_BASES[index / 15 % 15],
+                    IUPAC_BASES[index % 15],
+                ];
+                let text = std::str::from_utf8(&triplet).unwrap();
+                let symbol = codon_index(&triplet).map(|codon| code.amino_acids.as_bytes()[codon]);
+                let concrete = symbol.is_some();
+                let expansions: Vec<String> = make_iupac_set(&triplet)
+                    .into_iter()
+                    .filter(|codon| codon_index(codon.as_bytes()).is_some())
+                    .collect();
+
+                assert_eq!(signature.is_stop(&triplet), symbol == Some(b'*'));
+                assert_eq!(
+                    signature.translate(&triplet),
+                    symbol.map_or('X', char::from)
+                );
+
+                for aa in residues.chars() {
+                    let codons = codons_of(aa);
+                    let any = has_iupac_match(text, codons.iter().map(String::as_str));
+                    let all = expansions.iter().all(|codon| codons.contains(codon));
+                    for (ambiguity, accepted) in [
+                        (Ambiguity::Any, any),
+                        (Ambiguity::All, all),
+                        (Ambiguity::Reject, false),
+                    ] {
+                        let expected = match (concrete, any, accepted) {
+                            (true, true, _) => CodonMatch::Exact,
+                            (false, _, true) => CodonMatch::Ambiguous,
+                            _ => CodonMatch::Mismatch,
+                        };
+                        assert_eq!(
+                            signature.check(aa, &triplet, ambiguity),
+                            expected,
+                            "table {} {} under {} ({:?})",
+                            code.id,
+                            text,
+                            aa,
+                            ambiguity
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn aligns(aligner: &CodonAligner, aa: &str, nt: &str) -> bool {
+        aligner
+            .check_codon_record(1, PairedRecord::new(">a", ">a", aa, ">a", nt))
+            .is_ok()
+    }
+
+    #[test]
+    fn table_start_codons_are_read_as_m_at_the_first_residue() {
+        let aligner = CodonAligner::from_table_number(11)
+            .unwrap()
+            .with_start_codons(StartCodons::Table);
+        for start in ["TTG", "GTG", "ATT"] {
+            let record = align(&aligner, "-MK", &format!("{}AAA", start));
+            assert_eq!(record.metadata.alternative_start.as_deref(), Some(start));
+        }
+        assert!(!aligns(&aligner, "MK", "CTCAAA"));
+        // Only the first residue may use an alternative initiator.
+        assert!(!aligns(&aligner, "MMK", "ATGGTGAAA"));
+        let off = aligner.with_start_codons(StartCodons::Off);
+        assert!(!aligns(&off, "MK", "GTGAAA"));
+    }
+
+    #[test]
+    fn listed_start_codons_apply_to_any_table() {
+        for aligner in aligners() {
+            let aligner = aligner.with_start_codons(StartCodons::Codons(vec!["CTG".to_string()]));
+            let record = align(&aligner, "MK", "CTGAAA");
+            assert_eq!(record.metadata.alternative_start.as_deref(), Some("CTG"));
+            assert!(!aligns(&aligner, "MK", "GTGAAA"));
+            assert!(!aligns(&aligner, "MMK", "ATGCTGAAA"));
+        }
+    }
+
+    #[test]
+    fn custom_tables_have_no_table_start_codons() {
+        let aligner = CodonAligner::from_gene_table(&standard_gene_table(), "<test>")
+            .unwrap()
+            .with_start_codons(StartCodons::Table);
+        assert!(!aligns(&aligner, "MK", "GTGAAA"));
+        let record = align(&aligner, "MK", "ATGAAA");
+        assert!(record.metadata.alternative_start.is_none());
+    }
+
+    #[test]
+    fn terminal_stop_must_be_concrete() {
+        for aligner in aligners() {
+            let aligner = aligner.with_terminal_stop(TerminalStop::Strip);
+            let record = align(&aligner, "MK", "ATGAAATAA");
+            assert_eq!(record.sequence, "ATGAAA");
+            assert_eq!(record.metadata.terminal_stop.as_deref(), Some("TAA"));
+
+            let result = aligner
+                .check_codon_record(1, PairedRecord::new(">a", ">a", "MK", ">a", "ATGAAANNN"));
+            assert!(result.is_err());
+        }
+    }
+
+    #[test]
+    fn frame_detection_reports_non_ascii_records() {
+        for aligner in aligners() {
+            let aligner = aligner.with_frame_detection(true);
+            let result =
+                aligner.check_codon_record(1, PairedRecord::new(">a", ">a", "MK", ">a", "CATGAAé"));
+            assert!(result.is_err());
+        }
+    }
+
+    #[test]
+    fn flank_trimming_skips_non_ascii_windows() {
+        for aligner in aligners() {
+            let aligner = aligner.with_flank_trimming(true);
+            let record = align(&aligner, "MK", "éCCATGAAAGG");
+            assert_eq!(record.sequence, "ATGAAA");
+            assert_eq!(record.metadata.coding_region, Some(4..10));
+
+            let aligner = aligner.with_frame_detection(true);
+            let result = aligner
+                .check_codon_record(1, PairedRecord::new(">a", ">a", "MK", ">a", "ATéGAAAGG"));
+            assert!(result.is_err());
+        }
+    }
+
+    #[test]
+    fn mismatch_mask_must_be_three_characters() {
+        assert!(MismatchMasking::with_mask("N").is_none());
+        assert!(MismatchMasking::with_mask("NNNN").is_none());
+        for aligner in aligners() {
+            let masking = MismatchMasking::with_mask("???");
+            let aligner = aligner.with_mismatch_masking(masking);
+            let record = align(&aligner, "MKK", "ATGCCCAAA");
+            assert_eq!(record.sequence, "ATG???AAA");
+            assert_eq!(record.metadata.masked_codons.len(), 1);
+        }
+    }
+
+    #[test]
+    fn concrete_stops_are_still_counted() {
+        for aligner in aligners() {
+            let aligner = aligner.with_internal_stops(InternalStops::Mask);
+            let record = align(&aligner, "M*K*", "ATGTAAAAATGA");
+            assert_eq!(record.sequence, "ATGNNNAAATGA");
+            assert_eq!(record.metadata.stop_codons, 2);
+        }
+    }
 }
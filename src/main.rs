@@ -0,0 +1,659 @@
+use std::io::Write;
+use std::process::ExitCode;
+
+use std::path::Path;
+
+use pr2codon::{
+    discover_jobs, read_genetic_codes, read_manifest, select_genetic_code, write_output, Ambiguity,
+    BatchOptions, CodonAligner, CodonTable, Compression, FrameshiftAlignment, IdExtractor,
+    InternalStops, MismatchMasking, Pairing, Pn2CodonError, StartCodons, TerminalStop,
+    GENETIC_CODES,
+};
+
+const USAGE: &str = "\
+Usage: pn2codon [OPTIONS] <PROTEIN_ALIGNMENT> <NUCLEOTIDES> <OUTPUT>
+       pn2codon [OPTIONS] --aa-dir <DIR> --nt-dir <DIR> --out-dir <DIR>
+       pn2codon [OPTIONS] --manifest <TSV> --out-dir <DIR>
+
+Back-translates an aligned peptide FASTA file into a codon alignment using the
+matching nucleotide records. Inputs may be gzip or bgzip compressed. Use `-` as
+OUTPUT to write to stdout.
+
+Batch mode matches the files of --aa-dir and --nt-dir by stem (`gene1.fa.gz`
+has stem `gene1`), or reads `stem<TAB>aa_path<TAB>nt_path` lines from
+--manifest, and writes `<stem>.fa` per pair into --out-dir in parallel. A
+per-stem TSV summary goes to stdout.
+
+Options:
+  --table <N|NAME>       NCBI translation table number or name, e.g. 2 or
+                         vertebrate_mitochondrial [default: 1]
+  --list-tables          Print the built-in translation tables as TSV
+  --table-json <PATH>    Alias of --table-file for a JSON genetic code
+  --table-file <PATH>    Genetic code file, NCBI gc.prt or JSON; every codon
+                         must be assigned once. With several codes in the
+                         file, --table picks one by id or name
+  --pair-by <MODE>       position, header, first_token or regex [default: position]
+  --id-regex <REGEX>     Record ID pattern for --pair-by regex
+  --gzip                 Gzip the output regardless of its extension
+  --keep-going           Report every failed record and write the rest
+  --detect-frame         Read each nucleotide record in its best-matching frame
+  --trim-flanks          Cut untranslated flanks off over-long nucleotide records
+  --terminal-stop <MODE> reject, strip or keep a stop codon missing from the
+                         peptide [default: reject]
+  --start-codons <LIST>  Accept alternative initiation codons under a leading
+                         M: `table` for the table's own, or CODON,CODON,...
+  --ambiguity <MODE>     Accept a codon with IUPAC codes if `any` or `all` of
+                         its expansions encode the residue, or `reject` it
+                         [default: any]
+  --mask-mismatches      Replace codons that do not encode their residue
+                         instead of failing the record; each is reported on
+                         stderr outside batch mode
+  --mismatch-mask <SEQ>  Three characters to write for a masked codon
+                         [default: NNN]
+  --mismatch-limit <N>   Most codons masked per record; one more fails it
+  --internal-stops <MODE>
+                         keep, mask (NNN) or gap (---) stop codons inside the
+                         coding sequence, for PAML/HyPhy [default: keep]
+  --drop-terminal-stop   Write the stop codon ending each sequence as gaps
+  --frameshifts          Realign records that do not hold one codon per residue,
+                         allowing frameshifts: missing bases are written as !,
+                         extra bases are dropped, and shifts are reported on
+                         stderr outside batch mode
+  --frameshift-penalty <N>
+                         Cost of one frameshift, against 1 per codon that does
+                         not encode its residue [default: 5]
+  --report <PATH>        Write per-record details as TSV with columns header,
+                         frame, coding_start and coding_end (kept coding
+                         region, 0-based end-exclusive), terminal_stop,
+                         alternative_start, ambiguous_codons, masked_positions,
+                         stop_codons and frameshifts. Not available in batch
+                         mode, whose summary leaves these details out
+  --threads <N>          Worker threads [default: one per core]
+  -h, --help             Print this help
+
+Exit codes:
+  0 success, 2 usage, 3 unreadable or malformed input, 4 invalid codon table,
+  5 records could not be paired, 6 header mismatch, 7 length mismatch,
+  8 amino acid missing from codon table, 9 codon mismatch, 10 output not written,
+  11 one or more batch stems failed
+";
+
+const EXIT_USAGE: u8 = 2;
+const EXIT_BATCH_FAILED: u8 = 11;
+
+/// Exit status for each failure class, as listed in `USAGE`.
+fn exit_code(err: &Pn2CodonError) -> u8 {
+    match err {
+        Pn2CodonError::Io { .. }
+        | Pn2CodonError::InvalidFasta { .. }
+        | Pn2CodonError::InvalidManifest { .. } => 3,
+        Pn2CodonError::InvalidTable { .. }
+        | Pn2CodonError::UnknownTableName { .. }
+        | Pn2CodonError::InvalidGeneTable { .. } => 4,
+        Pn2CodonError::RecordCountMismatch { .. }
+        | Pn2CodonError::Pairing(_)
+        | Pn2CodonError::UnpairedFile { .. }
+        | Pn2CodonError::DuplicateStems { .. } => 5,
+        Pn2CodonError::InvalidIdPattern { .. } => EXIT_USAGE,
+        Pn2CodonError::HeaderMismatch { .. } => 6,
+        Pn2CodonError::LengthMismatch { .. } => 7,
+        Pn2CodonError::MissingAminoAcid { .. } => 8,
+        Pn2CodonError::CodonMismatch { .. } => 9,
+        Pn2CodonError::Write { .. } | Pn2CodonError::ThreadPool { .. } => 10,
+    }
+}
+
+enum Mode {
+    Single {
+        aa_path: String,
+        nt_path: String,
+        output: String,
+    },
+    Directories {
+        aa_dir: String,
+        nt_dir: String,
+        out_dir: String,
+    },
+    Manifest {
+        manifest: String,
+        out_dir: String,
+    },
+}
+
+struct Args {
+    mode: Mode,
+    table: Option<String>,
+    table_file: Option<String>,
+    pair_by: Option<String>,
+    id_regex: Option<String>,
+    gzip: bool,
+    keep_going: bool,
+    detect_frame: bool,
+    trim_flanks: bool,
+    terminal_stop: TerminalStop,
+    start_codons: StartCodons,
+    ambiguity: Ambiguity,
+    mismatch_masking: Option<MismatchMasking>,
+    internal_stops: InternalStops,
+    drop_terminal_stop: bool,
+    frameshifts: Option<FrameshiftAlignment>,
+    report: Option<String>,
+    threads: Option<usize>,
+}
+
+enum Parsed {
+    Run(Box<Args>),
+    Help,
+    ListTables,
+}
+
+fn parse_args<I: Iterator<Item = String>>(mut argv: I) -> Result<Parsed, String> {
+    let mut table = None;
+    let mut table_json = None;
+    let mut table_file = None;
+    let mut pair_by = None;
+    let mut id_regex = None;
+    let mut gzip = false;
+    let mut keep_going = false;
+    let mut detect_frame = false;
+    let mut trim_flanks = false;
+    let mut terminal_stop = TerminalStop::Reject;
+    let mut start_codons = StartCodons::Off;
+    let mut ambiguity = Ambiguity::Any;
+    let mut mask_mismatches = false;
+    let mut mismatch_mask = None;
+    let mut mismatch_limit = None;
+    let mut internal_stops = InternalStops::Keep;
+    let mut drop_terminal_stop = false;
+    let mut frameshifts = false;
+    let mut frameshift_penalty = None;
+    let mut report = None;
+    let mut threads = None;
+    let mut aa_dir = None;
+    let mut nt_dir = None;
+    let mut manifest = None;
+    let mut out_dir = None;
+    let mut positional = Vec::new();
+
+    while let Some(arg) = argv.next() {
+        let mut value_for = |name: &str| {
+            argv.next()
+                .ok_or_else(|| format!("{} requires a value", name))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Parsed::Help),
+            "--list-tables" => return Ok(Parsed::ListTables),
+            "--table" => table = Some(value_for("--table")?),
+            "--threads" => {
+                let value = value_for("--threads")?;
+                let count = value
+                    .parse()
+                    .ok()
+                    .filter(|&count: &usize| count > 0)
+                    .ok_or_else(|| {
+                        format!("--threads expects a positive number, got '{}'", value)
+                    })?;
+                threads = Some(count);
+            }
+            "--table-json" => table_json = Some(value_for("--table-json")?),
+            "--table-file" => table_file = Some(value_for("--table-file")?),
+            "--pair-by" => pair_by = Some(value_for("--pair-by")?),
+            "--id-regex" => id_regex = Some(value_for("--id-regex")?),
+            "--aa-dir" => aa_dir = Some(value_for("--aa-dir")?),
+            "--nt-dir" => nt_dir = Some(value_for("--nt-dir")?),
+            "--manifest" => manifest = Some(value_for("--manifest")?),
+            "--out-dir" => out_dir = Some(value_for("--out-dir")?),
+            "--gzip" => gzip = true,
+            "--keep-going" => keep_going = true,
+            "--detect-frame" => detect_frame = true,
+            "--trim-flanks" => trim_flanks = true,
+            "--terminal-stop" => {
+                terminal_stop = match value_for("--terminal-stop")?.as_str() {
+                    "reject" => TerminalStop::Reject,
+                    "strip" => TerminalStop::Strip,
+                    "keep" => TerminalStop::Keep,
+                    other => {
+                        return Err(format!(
+                            "unknown --terminal-stop '{}'; expected reject, strip or keep",
+                            other
+                        ))
+                    }
+                }
+            }
+            "--start-codons" => {
+                let value = value_for("--start-codons")?;
+                start_codons = if value == "table" {
+                    StartCodons::Table
+                } else {
+                    let codons: Vec<String> = value.split(',').map(str::to_string).collect();
+                    if let Some(bad) = codons.iter().find(|codon| codon.len() != 3) {
+                        return Err(format!(
+                            "--start-codons expects `table` or comma-separated codons, got '{}'",
+                            bad
+                        ));
+                    }
+                    StartCodons::Codons(codons)
+                }
+            }
+            "--ambiguity" => {
+                ambiguity = match value_for("--ambiguity")?.as_str() {
+                    "any" => Ambiguity::Any,
+                    "all" => Ambiguity::All,
+                    "reject" => Ambiguity::Reject,
+                    other => {
+                        return Err(format!(
+                            "unknown --ambiguity '{}'; expected any, all or reject",
+                            other
+                        ))
+                    }
+                }
+            }
+            "--mask-mismatches" => mask_mismatches = true,
+            "--mismatch-mask" => {
+                let value = value_for("--mismatch-mask")?;
+                let masking = MismatchMasking::with_mask(&value).ok_or_else(|| {
+                    format!("--mismatch-mask expects three characters, got '{}'", value)
+                })?;
+                mismatch_mask = Some(masking);
+            }
+            "--mismatch-limit" => {
+                let value = value_for("--mismatch-limit")?;
+                let limit = value
+                    .parse()
+                    .map_err(|_| format!("--mismatch-limit expects a number, got '{}'", value))?;
+                mismatch_limit = Some(limit);
+            }
+            "--internal-stops" => {
+                internal_stops = match value_for("--internal-stops")?.as_str() {
+                    "keep" => InternalStops::Keep,
+                    "mask" => InternalStops::Mask,
+                    "gap" => InternalStops::Gap,
+                    other => {
+                        return Err(format!(
+                            "unknown --internal-stops '{}'; expected keep, mask or gap",
+                            other
+                        ))
+                    }
+                }
+            }
+            "--drop-terminal-stop" => drop_terminal_stop = true,
+            "--frameshifts" => frameshifts = true,
+            "--frameshift-penalty" => {
+                let value = value_for("--frameshift-penalty")?;
+                let penalty = value
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|penalty| *penalty >= 1)
+                    .ok_or_else(|| {
+                        format!(
+                            "--frameshift-penalty expects a number of at least 1, got '{}'",
+                            value
+                        )
+                    })?;
+                frameshift_penalty = Some(penalty);
+            }
+            "--report" => report = Some(value_for("--report")?),
+            "-" => positional.push(arg),
+            flag if flag.starts_with('-') => return Err(format!("unknown option '{}'", flag)),
+            _ => positional.push(arg),
+        }
+    }
+
+    if table_json.is_some() && table_file.is_some() {
+        return Err("--table-json and --table-file cannot be used together".to_string());
+    }
+    let table_file = table_file.or(table_json);
+    if !mask_mismatches && (mismatch_mask.is_some() || mismatch_limit.is_some()) {
+        return Err("--mismatch-mask and --mismatch-limit require --mask-mismatches".to_string());
+    }
+    let mismatch_masking = mask_mismatches.then(|| {
+        let mut masking = mismatch_mask.unwrap_or_default();
+        masking.limit = mismatch_limit;
+        masking
+    });
+    if !frameshifts && frameshift_penalty.is_some() {
+        return Err("--frameshift-penalty requires --frameshifts".to_string());
+    }
+    let frameshifts = frameshifts.then(|| {
+        let defaults = FrameshiftAlignment::default();
+        FrameshiftAlignment {
+            penalty: frameshift_penalty.unwrap_or(defaults.penalty),
+            ..defaults
+        }
+    });
+    let batch = aa_dir.is_some() || nt_dir.is_some() || manifest.is_some() || out_dir.is_some();
+    if batch && !positional.is_empty() {
+        return Err("batch mode takes no positional arguments".to_string());
+    }
+    if keep_going && batch {
+        return Err("--keep-going is not available in batch mode".to_string());
+    }
+    if report.is_some() && batch {
+        return Err("--report is not available in batch mode".to_string());
+    }
+    let mode = match (aa_dir, nt_dir, manifest, out_dir) {
+        (None, None, None, None) => match <[String; 3]>::try_from(positional) {
+            Ok([aa_path, nt_path, output]) => Mode::Single {
+                aa_path,
+                nt_path,
+                output,
+            },
+            Err(positional) => {
+                return Err(format!(
+                    "expected 3 positional arguments, got {}",
+                    positional.len()
+                ))
+            }
+        },
+        (Some(aa_dir), Some(nt_dir), None, Some(out_dir)) => Mode::Directories {
+            aa_dir,
+            nt_dir,
+            out_dir,
+        },
+        (None, None, Some(manifest), Some(out_dir)) => Mode::Manifest { manifest, out_dir },
+        (_, _, _, None) => return Err("batch mode requires --out-dir".to_string()),
+        (_, _, Some(_), _) => {
+            return Err("--manifest cannot be combined with --aa-dir or --nt-dir".to_string())
+        }
+        _ => return Err("--aa-dir and --nt-dir must be given together".to_string()),
+    };
+    Ok(Parsed::Run(Box::new(Args {
+        mode,
+        table,
+        table_file,
+        pair_by,
+        id_regex,
+        gzip,
+        keep_going,
+        detect_frame,
+        trim_flanks,
+        terminal_stop,
+        start_codons,
+        ambiguity,
+        mismatch_masking,
+        internal_stops,
+        drop_terminal_stop,
+        frameshifts,
+        report,
+        threads,
+    })))
+}
+
+fn pairing(args: &Args) -> Result<Pairing, String> {
+    match (
+        args.pair_by.as_deref().unwrap_or("position"),
+        &args.id_regex,
+    ) {
+        ("position", None) => Ok(Pairing::Position),
+        ("header", None) => Ok(Pairing::ById(IdExtractor::FullHeader)),
+        ("first_token", None) => Ok(Pairing::ById(IdExtractor::FirstToken)),
+        ("regex", Some(pattern)) => IdExtractor::regex(pattern)
+            .map(Pairing::ById)
+            .map_err(|err| err.to_string()),
+        ("regex", None) => Err("--pair-by regex requires --id-regex".to_string()),
+        (_, Some(_)) => Err("--id-regex is only used with --pair-by regex".to_string()),
+        (other, None) => Err(format!(
+            "unknown --pair-by '{}'; expected position, header, first_token or regex",
+            other
+        )),
+    }
+}
+
+/// `--list-tables` output: one row per built-in genetic code.
+fn table_listing() -> String {
+    let mut out = String::from("id\tname\tshort_name\taliases\tstart_codons\tstop_codons\n");
+    for code in GENETIC_CODES {
+        out.push_str(&format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\n",
+            code.id,
+            code.name,
+            code.short_name.unwrap_or_default(),
+            code.aliases.join(","),
+            code.start_codons().join(","),
+            code.stop_codons().join(",")
+        ));
+    }
+    out
+}
+
+fn compression(args: &Args) -> Compression {
+    if args.gzip {
+        Compression::Gzip
+    } else {
+        Compression::Auto
+    }
+}
+
+fn emit(args: &Args, output: &str, file: &str) -> Result<(), Pn2CodonError> {
+    if output == "-" {
+        let mut stdout = std::io::stdout().lock();
+        return stdout
+            .write_all(file.as_bytes())
+            .and_then(|_| stdout.flush())
+            .map_err(|err| Pn2CodonError::Write {
+                path: "<stdout>".to_string(),
+                message: err.to_string(),
+            });
+    }
+    write_output(output, file, compression(args))
+}
+
+fn run_single(
+    args: &Args,
+    aligner: CodonAligner,
+    aa_path: &str,
+    nt_path: &str,
+    output: &str,
+) -> Result<(), Pn2CodonError> {
+    let aligner = aligner
+        .with_source_paths(aa_path, nt_path)
+        .with_threads(args.threads)?;
+
+    if !args.keep_going
+        && args.report.is_none()
+        && args.mismatch_masking.is_none()
+        && args.frameshifts.is_none()
+    {
+        let file = aligner.align_files(aa_path, nt_path)?;
+        return emit(args, output, &file);
+    }
+
+    let report = aligner.align_all(aligner.read_records(aa_path, nt_path)?);
+    if !args.keep_going {
+        if let Some(first) = report.errors().next() {
+            return Err(first.clone());
+        }
+    }
+    emit(args, output, &report.to_fasta())?;
+    if let Some(path) = &args.report {
+        write_output(path, &report.metadata_tsv(), Compression::Auto)?;
+    }
+    for record in &report.records {
+        for masked in &record.metadata.masked_codons {
+            eprint!("{}", masked);
+        }
+        if !record.metadata.frameshifts.is_empty() {
+            let shifts: Vec<String> = record
+                .metadata
+                .frameshifts
+                .iter()
+                .map(|shift| shift.to_string())
+                .collect();
+            eprintln!(
+                "pn2codon: {}: frameshifts at {}",
+                record.header.trim_start_matches('>'),
+                shifts.join(", ")
+            );
+        }
+    }
+    let Some(first) = report.errors().next().cloned() else {
+        return Ok(());
+    };
+    // The first error is printed by `main`, like in strict mode.
+    for err in report.errors().skip(1) {
+        eprint!("{}", err);
+    }
+    eprintln!(
+        "pn2codon: {} of {} records failed; first failure follows",
+        report.failures.len(),
+        report.failures.len() + report.records.len()
+    );
+    Err(first)
+}
+
+/// Runs a batch and prints its summary. Returns whether every stem, and
+/// every file found in the directories, succeeded.
+fn run_batch(args: &Args, aligner: CodonAligner, out_dir: &str) -> Result<bool, Pn2CodonError> {
+    let (jobs, unpaired) = match &args.mode {
+        Mode::Directories { aa_dir, nt_dir, .. } => {
+            discover_jobs(Path::new(aa_dir), Path::new(nt_dir))?
+        }
+        Mode::Manifest { manifest, .. } => (read_manifest(Path::new(manifest))?, Vec::new()),
+        Mode::Single { .. } => unreachable!("run_batch is only called in batch mode"),
+    };
+    let options = BatchOptions {
+        threads: args.threads,
+        compression: if args.gzip {
+            Compression::Gzip
+        } else {
+            Compression::Plain
+        },
+    };
+    let summary = aligner.align_batch(&jobs, Path::new(out_dir), options)?;
+
+    for err in unpaired.iter().chain(
+        summary
+            .outcomes
+            .iter()
+            .filter_map(|o| o.result.as_ref().err()),
+    ) {
+        eprint!("{}", err);
+    }
+    print!("{}", summary.to_tsv());
+    eprintln!(
+        "pn2codon: {} of {} stems succeeded, {} failed, {} unpaired files",
+        summary.succeeded(),
+        summary.outcomes.len(),
+        summary.failed(),
+        unpaired.len()
+    );
+    Ok(summary.failed() == 0 && unpaired.is_empty())
+}
+
+fn run(args: &Args, pairing: Pairing) -> Result<ExitCode, Pn2CodonError> {
+    let table = match (&args.table_file, &args.table) {
+        (Some(path), key) => {
+            select_genetic_code(read_genetic_codes(path)?, key.as_deref(), path)?.codon_table()
+        }
+        (None, None) => CodonTable::from_table_number(1)?,
+        (None, Some(table)) => match table.parse() {
+            Ok(number) => CodonTable::from_table_number(number)?,
+            Err(_) => CodonTable::from_table_name(table)?,
+        },
+    };
+    let aligner = CodonAligner::new(table)
+        .with_pairing(pairing)
+        .with_frame_detection(args.detect_frame)
+        .with_flank_trimming(args.trim_flanks)
+        .with_terminal_stop(args.terminal_stop)
+        .with_start_codons(args.start_codons.clone())
+        .with_ambiguity(args.ambiguity)
+        .with_mismatch_masking(args.mismatch_masking.clone())
+        .with_internal_stops(args.internal_stops)
+        .with_terminal_stop_dropped(args.drop_terminal_stop)
+        .with_frameshifts(args.frameshifts);
+
+    match &args.mode {
+        Mode::Single {
+            aa_path,
+            nt_path,
+            output,
+        } => run_single(args, aligner, aa_path, nt_path, output).map(|()| ExitCode::SUCCESS),
+        Mode::Directories { out_dir, .. } | Mode::Manifest { out_dir, .. } => {
+            if run_batch(args, aligner, out_dir)? {
+                Ok(ExitCode::SUCCESS)
+            } else {
+                Ok(ExitCode::from(EXIT_BATCH_FAILED))
+            }
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(Parsed::Run(args)) => args,
+        Ok(Parsed::Help) => {
+            print!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Ok(Parsed::ListTables) => {
+            print!("{}", table_listing());
+            return ExitCode::SUCCESS;
+        }
+        Err(message) => {
+            eprintln!("pn2codon: {}\n\n{}", message, USAGE);
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+    let pairing = match pairing(&args) {
+        Ok(pairing) => pairing,
+        Err(message) => {
+            eprintln!("pn2codon: {}", message);
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+
+    match run(&args, pairing) {
+        Ok(code) => code,
+        Err(err) => {
+            eprint!("{}", err);
+            ExitCode::from(exit_code(&err))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Parsed, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn table_json_is_an_alias_of_table_file() {
+        for flag in ["--table-json", "--table-file"] {
+            let parsed = parse(&[
+                "--table",
+                "2",
+                flag,
+                "codes.json",
+                "aa.fa",
+                "nt.fa",
+                "out.fa",
+            ]);
+            match parsed {
+                Ok(Parsed::Run(args)) => {
+                    assert_eq!(args.table.as_deref(), Some("2"));
+                    assert_eq!(args.table_file.as_deref(), Some("codes.json"));
+                }
+                Ok(_) => panic!("{} did not parse to a run", flag),
+                Err(err) => panic!("{}: {}", flag, err),
+            }
+        }
+    }
+
+    #[test]
+    fn table_json_and_table_file_are_exclusive() {
+        let parsed = parse(&[
+            "--table-json",
+            "a.json",
+            "--table-file",
+            "gc.prt",
+            "aa.fa",
+            "nt.fa",
+            "out.fa",
+        ]);
+        assert!(matches!(parsed, Err(err) if err.contains("cannot be used together")));
+    }
+}
@@ -0,0 +1,246 @@
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+
+use crate::{FastaRecord, PairedRecord, PairingIssues, Pn2CodonError};
+
+/// How the record ID is taken from a FASTA header.
+#[derive(Clone, Debug, Default)]
+pub enum IdExtractor {
+    /// The whole header line.
+    #[default]
+    FullHeader,
+    /// Everything up to the first whitespace.
+    FirstToken,
+    /// The first capture group of the regex, or the whole match when the
+    /// pattern has no groups. Headers the regex does not match use the
+    /// whole header.
+    Regex(Regex),
+}
+
+impl IdExtractor {
+    pub fn regex(pattern: &str) -> Result<Self, Pn2CodonError> {
+        Regex::new(pattern)
+            .map(IdExtractor::Regex)
+            .map_err(|err| Pn2CodonError::InvalidIdPattern {
+                pattern: pattern.to_string(),
+                message: err.to_string(),
+            })
+    }
+
+    pub fn extract<'h>(&self, header: &'h str) -> &'h str {
+        let header = header.trim();
+        match self {
+            IdExtractor::FullHeader => header,
+            IdExtractor::FirstToken => header.split_whitespace().next().unwrap_or(""),
+            IdExtractor::Regex(regex) => match regex.captures(header) {
+                Some(captures) => captures
+                    .get(1)
+                    .or_else(|| captures.get(0))
+                    .map_or(header, |m| m.as_str()),
+                None => header,
+            },
+        }
+    }
+}
+
+static FULL_HEADER: IdExtractor = IdExtractor::FullHeader;
+
+/// How peptide and nucleotide records read from files are matched up.
+#[derive(Clone, Debug, Default)]
+pub enum Pairing {
+    /// The n-th peptide record goes with the n-th nucleotide record.
+    #[default]
+    Position,
+    /// Records are matched by the ID taken from their headers.
+    ById(IdExtractor),
+}
+
+impl Pairing {
+    /// The extractor used to compare a pair's headers.
+    pub fn id_extractor(&self) -> &IdExtractor {
+        match self {
+            Pairing::Position => &FULL_HEADER,
+            Pairing::ById(id_extractor) => id_extractor,
+        }
+    }
+}
+
+fn duplicate_ids<'r>(records: &'r [FastaRecord], id_extractor: &IdExtractor) -> Vec<&'r str> {
+    let mut seen = HashSet::new();
+    let mut duplicates = Vec::new();
+    for record in records {
+        let id = id_extractor.extract(&record.header);
+        if !seen.insert(id) && !duplicates.contains(&id) {
+            duplicates.push(id);
+        }
+    }
+    duplicates
+}
+
+/// Matches peptide and nucleotide records by ID.
+///
+/// Duplicate IDs in either input, and IDs present in only one of them, are
+/// all reported together before any translation happens. Pairs come out in
+/// peptide order, each headed by its peptide header.
+pub fn pair_records_by_id(
+    aa_records: Vec<FastaRecord>,
+    nt_records: Vec<FastaRecord>,
+    id_extractor: &IdExtractor,
+    aa_source: &str,
+    nt_source: &str,
+) -> Result<Vec<PairedRecord>, Pn2CodonError> {
+    let duplicate_aa_ids = duplicate_ids(&aa_records, id_extractor);
+    let duplicate_nt_ids = duplicate_ids(&nt_records, id_extractor);
+
+    let nt_index: HashMap<&str, usize> = nt_records
+        .iter()
+        .enumerate()
+        .map(|(index, record)| (id_extractor.extract(&record.header), index))
+        .collect();
+    let aa_ids: HashSet<&str> = aa_records
+        .iter()
+        .map(|record| id_extractor.extract(&record.header))
+        .collect();
+
+    let only_in_aa: Vec<&str> = aa_records
+        .iter()
+        .map(|record| id_extractor.extract(&record.header))
+        .filter(|id| !nt_index.contains_key(id))
+        .collect();
+    let only_in_nt: Vec<&str> = nt_records
+        .iter()
+        .map(|record| id_extractor.extract(&record.header))
+        .filter(|id| !aa_ids.contains(id))
+        .collect();
+
+    if !duplicate_aa_ids.is_empty()
+        || !duplicate_nt_ids.is_empty()
+        || !only_in_aa.is_empty()
+        || !only_in_nt.is_empty()
+    {
+        let to_owned = |ids: Vec<&str>| ids.into_iter().map(str::to_string).collect();
+        return Err(Pn2CodonError::Pairing(Box::new(PairingIssues {
+            aa_source: aa_source.to_string(),
+            nt_source: nt_source.to_string(),
+            duplicate_aa_ids: to_owned(duplicate_aa_ids),
+            duplicate_nt_ids: to_owned(duplicate_nt_ids),
+            only_in_aa: to_owned(only_in_aa),
+            only_in_nt: to_owned(only_in_nt),
+        })));
+    }
+
+    let order: Vec<usize> = aa_records
+        .iter()
+        .map(|record| nt_index[id_extractor.extract(&record.header)])
+        .collect();
+    let mut nt_slots: Vec<Option<FastaRecord>> = nt_records.into_iter().map(Some).collect();
+
+    Ok(aa_records
+        .into_iter()
+        .zip(order)
+        .map(|(aa, nt_position)| {
+            let nt = nt_slots[nt_position].take().unwrap_or_default();
+            PairedRecord {
+                header: format!(">{}", aa.header),
+                aa_header: aa.header,
+                aa: aa.sequence,
+                nt_header: nt.header,
+                nt: nt.sequence,
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records(headers: &[&str]) -> Vec<FastaRecord> {
+        headers
+            .iter()
+            .map(|header| FastaRecord {
+                header: header.to_string(),
+                sequence: String::new(),
+            })
+            .collect()
+    }
+
+    fn pair(aa: &[&str], nt: &[&str], id_extractor: &IdExtractor) -> Vec<(String, String)> {
+        pair_records_by_id(records(aa), records(nt), id_extractor, "aa.fa", "nt.fa")
+            .unwrap()
+            .into_iter()
+            .map(|record| (record.aa_header, record.nt_header))
+            .collect()
+    }
+
+    fn owned(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(aa, nt)| (aa.to_string(), nt.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn pairs_by_each_id_extractor() {
+        assert_eq!(
+            pair(
+                &["g1 kinase", "g2"],
+                &["g2", "g1 kinase"],
+                &IdExtractor::FullHeader
+            ),
+            owned(&[("g1 kinase", "g1 kinase"), ("g2", "g2")])
+        );
+        assert_eq!(
+            pair(
+                &["g1 kinase", "g2 x"],
+                &["g2 cds", "g1 cds"],
+                &IdExtractor::FirstToken
+            ),
+            owned(&[("g1 kinase", "g1 cds"), ("g2 x", "g2 cds")])
+        );
+        let regex = IdExtractor::regex(r"\|(g\d+)\|").unwrap();
+        assert_eq!(
+            pair(&["sp|g1|A", "sp|g2|B"], &["nt|g2|", "nt|g1|"], &regex),
+            owned(&[("sp|g1|A", "nt|g1|"), ("sp|g2|B", "nt|g2|")])
+        );
+    }
+
+    #[test]
+    fn pairs_come_out_in_peptide_order() {
+        assert_eq!(
+            pair(&["b", "c", "a"], &["a", "b", "c"], &IdExtractor::FullHeader),
+            owned(&[("b", "b"), ("c", "c"), ("a", "a")])
+        );
+    }
+
+    #[test]
+    fn reports_every_pairing_problem_at_once() {
+        let result = pair_records_by_id(
+            records(&["a", "a", "b", "x"]),
+            records(&["a", "b", "b", "y"]),
+            &IdExtractor::FullHeader,
+            "aa.fa",
+            "nt.fa",
+        );
+        match result {
+            Err(Pn2CodonError::Pairing(issues)) => {
+                assert_eq!(issues.duplicate_aa_ids, ["a"]);
+                assert_eq!(issues.duplicate_nt_ids, ["b"]);
+                assert_eq!(issues.only_in_aa, ["x"]);
+                assert_eq!(issues.only_in_nt, ["y"]);
+            }
+            other => panic!("expected a pairing error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn regex_without_a_group_uses_the_whole_match() {
+        let regex = IdExtractor::regex(r"g\d+").unwrap();
+        assert_eq!(regex.extract("sp|g12|kinase"), "g12");
+        assert_eq!(regex.extract(" no id here "), "no id here");
+        assert!(matches!(
+            IdExtractor::regex("("),
+            Err(Pn2CodonError::InvalidIdPattern { .. })
+        ));
+    }
+}
@@ -0,0 +1,1329 @@
+use pyo3::create_exception;
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use std::path::Path;
+
+use crate::translate::{translate_batch_with_signature, translate_with_signature};
+use crate::{
+    discover_jobs, genetic_code_by_name, read_genetic_codes, read_manifest, select_genetic_code,
+    AlignmentReport, Ambiguity, BatchOptions, CodonAligner, CodonStream, CodonTable, Compression,
+    CustomGeneticCode, FrameshiftAlignment, FrameshiftKind, GeneTable, GeneTableReport,
+    IdExtractor, InternalStops, MismatchMasking, PairedRecord, Pairing, PartialCodon,
+    Pn2CodonError as RustError, StartCodons, TerminalStop, TranslateOptions, GENETIC_CODES,
+};
+
+/// `header -> ((aa_header, aa), (_, nt_header, nt))`
+type SignatureSeqs = OrderedSeqs<((String, String), (i32, String, String))>;
+/// `header -> ((aa_header, aa), (nt_header, nt))`
+type TableSeqs = OrderedSeqs<((String, String), (String, String))>;
+
+/// Records keyed by output header, in the caller's order.
+///
+/// Accepts either a dict (iterated in insertion order) or a sequence of
+/// `(header, value)` tuples, so output FASTA order and the sequence indices
+/// in error messages are reproducible.
+struct OrderedSeqs<T>(Vec<(String, T)>);
+
+impl<'source, T: FromPyObject<'source>> FromPyObject<'source> for OrderedSeqs<T> {
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        if let Ok(dict) = ob.downcast::<PyDict>() {
+            return dict
+                .iter()
+                .map(|(key, value)| Ok((key.extract()?, value.extract()?)))
+                .collect::<PyResult<Vec<(String, T)>>>()
+                .map(OrderedSeqs);
+        }
+        ob.extract::<Vec<(String, T)>>().map(OrderedSeqs)
+    }
+}
+
+/// A genetic code given by NCBI number (`2`), by name
+/// (`"Vertebrate Mitochondrial"`, `"vertebrate_mitochondrial"`, `"SGC1"`) or
+/// as a `GeneticCode` object, e.g. one from `load_genetic_code`.
+#[derive(FromPyObject)]
+enum TableKey {
+    Number(i32),
+    Name(String),
+    Code(PyGeneticCode),
+}
+
+impl TableKey {
+    fn genetic_code(&self) -> PyResult<CustomGeneticCode> {
+        match self {
+            TableKey::Number(id) => crate::genetic_code(*id)
+                .map(CustomGeneticCode::from)
+                .ok_or(RustError::InvalidTable { requested: *id }),
+            TableKey::Name(name) => genetic_code_by_name(name)
+                .map(CustomGeneticCode::from)
+                .ok_or_else(|| RustError::UnknownTableName {
+                    requested: name.clone(),
+                }),
+            TableKey::Code(code) => Ok(code.code.clone()),
+        }
+        .map_err(to_py_err)
+    }
+
+    fn aligner(&self) -> PyResult<CodonAligner> {
+        Ok(CodonAligner::new(self.genetic_code()?.codon_table()))
+    }
+}
+
+/// The `gene_table` argument of the `*_original_args` functions: an amino
+/// acid to codons dict, checked with `validate_gene_table` before use, or a
+/// `GeneticCode` object.
+#[derive(FromPyObject)]
+enum GeneTableArg {
+    Code(PyGeneticCode),
+    Table(GeneTable),
+}
+
+impl GeneTableArg {
+    fn aligner(self) -> PyResult<CodonAligner> {
+        let table = match self {
+            GeneTableArg::Code(code) => code.code.codon_table(),
+            GeneTableArg::Table(gene_table) => {
+                CodonTable::from_gene_table(&gene_table, "<gene_table>").map_err(to_py_err)?
+            }
+        };
+        Ok(CodonAligner::new(table))
+    }
+}
+
+create_exception!(pr2codon, Pn2CodonError, PyValueError);
+create_exception!(pr2codon, InvalidTableError, Pn2CodonError);
+create_exception!(pr2codon, InvalidGeneTableError, Pn2CodonError);
+create_exception!(pr2codon, HeaderMismatchError, Pn2CodonError);
+create_exception!(pr2codon, LengthMismatchError, Pn2CodonError);
+create_exception!(pr2codon, MissingAminoAcidError, Pn2CodonError);
+create_exception!(pr2codon, CodonMismatchError, Pn2CodonError);
+create_exception!(pr2codon, FastaReadError, Pn2CodonError);
+create_exception!(pr2codon, FastaFormatError, Pn2CodonError);
+create_exception!(pr2codon, OutputWriteError, Pn2CodonError);
+create_exception!(pr2codon, RecordCountMismatchError, Pn2CodonError);
+create_exception!(pr2codon, InvalidIdPatternError, Pn2CodonError);
+create_exception!(pr2codon, PairingError, Pn2CodonError);
+create_exception!(pr2codon, UnpairedFileError, Pn2CodonError);
+create_exception!(pr2codon, DuplicateStemError, Pn2CodonError);
+create_exception!(pr2codon, ManifestFormatError, Pn2CodonError);
+
+/// Raises the exception subclass matching `err`, with its structured fields
+/// attached as attributes so callers do not have to parse the message.
+fn to_py_err(err: RustError) -> PyErr {
+    let message = err.to_string();
+    let py_err = match &err {
+        RustError::InvalidTable { .. } | RustError::UnknownTableName { .. } => {
+            InvalidTableError::new_err(message)
+        }
+        RustError::InvalidGeneTable { .. } => InvalidGeneTableError::new_err(message),
+        RustError::HeaderMismatch { .. } => HeaderMismatchError::new_err(message),
+        RustError::LengthMismatch { .. } => LengthMismatchError::new_err(message),
+        RustError::MissingAminoAcid { .. } => MissingAminoAcidError::new_err(message),
+        RustError::CodonMismatch { .. } => CodonMismatchError::new_err(message),
+        RustError::Io { .. } => FastaReadError::new_err(message),
+        RustError::InvalidFasta { .. } => FastaFormatError::new_err(message),
+        RustError::Write { .. } => OutputWriteError::new_err(message),
+        RustError::RecordCountMismatch { .. } => RecordCountMismatchError::new_err(message),
+        RustError::InvalidIdPattern { .. } => InvalidIdPatternError::new_err(message),
+        RustError::Pairing(_) => PairingError::new_err(message),
+        RustError::UnpairedFile { .. } => UnpairedFileError::new_err(message),
+        RustError::DuplicateStems { .. } => DuplicateStemError::new_err(message),
+        RustError::InvalidManifest { .. } => ManifestFormatError::new_err(message),
+        RustError::ThreadPool { .. } => Pn2CodonError::new_err(message),
+    };
+    Python::with_gil(|py| {
+        let _ = set_error_attributes(py, &py_err, &err);
+    });
+    py_err
+}
+
+fn set_error_attributes(py: Python, py_err: &PyErr, err: &RustError) -> PyResult<()> {
+    let value = py_err.value(py);
+    value.setattr("title", err.title())?;
+    if let Some(record) = err.record() {
+        value.setattr("sequence_index", record.sequence_index)?;
+        value.setattr("aa_source", &record.aa_source)?;
+        value.setattr("aa_header", &record.aa_header)?;
+        value.setattr("nt_source", &record.nt_source)?;
+        value.setattr("nt_header", &record.nt_header)?;
+    }
+    match err {
+        RustError::InvalidTable { requested } => {
+            value.setattr("requested_table", *requested)?;
+        }
+        RustError::UnknownTableName { requested } => {
+            value.setattr("requested_table", requested)?;
+        }
+        RustError::InvalidGeneTable { source, .. } => {
+            value.setattr("source", source)?;
+        }
+        RustError::HeaderMismatch { .. } | RustError::ThreadPool { .. } => {}
+        RustError::Io { path, .. } | RustError::Write { path, .. } => {
+            value.setattr("path", path)?;
+        }
+        RustError::InvalidFasta { path, line, .. }
+        | RustError::InvalidManifest { path, line, .. } => {
+            value.setattr("path", path)?;
+            value.setattr("line", *line)?;
+        }
+        RustError::RecordCountMismatch {
+            aa_count, nt_count, ..
+        } => {
+            value.setattr("aa_count", *aa_count)?;
+            value.setattr("nt_count", *nt_count)?;
+        }
+        RustError::UnpairedFile {
+            stem,
+            path,
+            missing_in,
+        } => {
+            value.setattr("stem", stem)?;
+            value.setattr("path", path)?;
+            value.setattr("missing_in", missing_in)?;
+        }
+        RustError::DuplicateStems { dir, duplicates } => {
+            value.setattr("path", dir)?;
+            value.setattr("duplicates", duplicates.clone())?;
+        }
+        RustError::InvalidIdPattern { pattern, .. } => {
+            value.setattr("pattern", pattern)?;
+        }
+        RustError::Pairing(issues) => {
+            value.setattr("duplicate_aa_ids", issues.duplicate_aa_ids.clone())?;
+            value.setattr("duplicate_nt_ids", issues.duplicate_nt_ids.clone())?;
+            value.setattr("only_in_aa", issues.only_in_aa.clone())?;
+            value.setattr("only_in_nt", issues.only_in_nt.clone())?;
+        }
+        RustError::LengthMismatch {
+            residues, nt_len, ..
+        } => {
+            value.setattr("residues", *residues)?;
+            value.setattr("nt_len", *nt_len)?;
+        }
+        RustError::MissingAminoAcid {
+            amino_acid,
+            alignment_position,
+            valid_symbols,
+            ..
+        } => {
+            value.setattr("amino_acid", amino_acid.to_string())?;
+            value.setattr("alignment_position", *alignment_position)?;
+            value.setattr("valid_symbols", valid_symbols.iter().collect::<String>())?;
+        }
+        RustError::CodonMismatch {
+            alignment_position,
+            nt_offset,
+            expected_aa,
+            codon,
+            ..
+        } => {
+            value.setattr("alignment_position", *alignment_position)?;
+            value.setattr("nt_offset", *nt_offset)?;
+            value.setattr("expected_aa", expected_aa.to_string())?;
+            value.setattr("codon", codon)?;
+        }
+    }
+    Ok(())
+}
+
+/// Parses the `pair_by`/`id_regex` keyword arguments of the file entry
+/// points. `pair_by` is one of `position`, `header`, `first_token` or
+/// `regex`; the last requires `id_regex`.
+fn parse_pairing(pair_by: &str, id_regex: Option<&str>) -> PyResult<Pairing> {
+    match (pair_by, id_regex) {
+        ("position", None) => Ok(Pairing::Position),
+        ("header", None) => Ok(Pairing::ById(IdExtractor::FullHeader)),
+        ("first_token", None) => Ok(Pairing::ById(IdExtractor::FirstToken)),
+        ("regex", Some(pattern)) => IdExtractor::regex(pattern)
+            .map(Pairing::ById)
+            .map_err(to_py_err),
+        ("regex", None) => Err(PyValueError::new_err("pair_by='regex' requires id_regex")),
+        (_, Some(_)) => Err(PyValueError::new_err(
+            "id_regex is only used with pair_by='regex'",
+        )),
+        (other, None) => Err(PyValueError::new_err(format!(
+            "unknown pair_by value '{}'; expected position, header, first_token or regex",
+            other
+        ))),
+    }
+}
+
+/// Parses the `compression` keyword argument: `auto`, `plain` or `gzip`.
+fn parse_compression(compression: &str) -> PyResult<Compression> {
+    match compression {
+        "auto" => Ok(Compression::Auto),
+        "plain" => Ok(Compression::Plain),
+        "gzip" => Ok(Compression::Gzip),
+        other => Err(PyValueError::new_err(format!(
+            "unknown compression '{}'; expected auto, plain or gzip",
+            other
+        ))),
+    }
+}
+
+/// Applies the alignment options shared by every `pn2codon*` entry point,
+/// passed as extra keyword arguments:
+///
+/// - `detect_frame` (bool): read each nucleotide record in whichever of its
+///   six frames best explains the peptide.
+/// - `trim_flanks` (bool): cut untranslated flanks off nucleotide records
+///   longer than the peptide needs.
+/// - `terminal_stop` (str): `reject` (default), `strip` or `keep` a stop
+///   codon that ends the nucleotide record but is missing from the peptide.
+/// - `start_codons` (bool, str or list of str): alternative initiation
+///   codons accepted under a leading `M`. `True` or `"table"` uses the
+///   table's own initiators; a list names the codons; `False`, `None` or
+///   `"off"` accepts none.
+/// - `ambiguity` (str): accept a codon with IUPAC ambiguity codes if `any`
+///   (default) or `all` of its expansions encode the residue, or `reject`
+///   it.
+/// - `mask_mismatches` (bool or str): replace codons that do not encode
+///   their residue with `NNN`, or with the given three characters, instead
+///   of failing the record. Masked codons are listed in
+///   `CodonRecord.masked_codons`.
+/// - `mismatch_limit` (int): most codons masked per record; one more fails
+///   the record. Requires `mask_mismatches`.
+/// - `internal_stops` (str): `keep` (default), `mask` as `NNN` or `gap` as
+///   `---` stop codons inside the coding sequence.
+/// - `drop_terminal_stop` (bool): write the stop codon ending the coding
+///   sequence as gaps, and leave out one kept with `terminal_stop`.
+/// - `frameshifts` (bool or int): realign records that do not hold one
+///   matching codon per residue, allowing frameshifts. An int sets the
+///   penalty of one shift (default 5). Missing bases are written as `!`,
+///   extra bases are dropped, and shifts are listed in
+///   `CodonRecord.frameshifts`.
+fn configure(aligner: CodonAligner, options: Option<&PyDict>) -> PyResult<CodonAligner> {
+    let mut aligner = aligner;
+    let Some(options) = options else {
+        return Ok(aligner);
+    };
+    let mut masking: Option<MismatchMasking> = None;
+    let mut mismatch_limit: Option<usize> = None;
+    for (key, value) in options.iter() {
+        let key: &str = key.extract()?;
+        aligner = match key {
+            "detect_frame" => aligner.with_frame_detection(value.extract()?),
+            "trim_flanks" => aligner.with_flank_trimming(value.extract()?),
+            "terminal_stop" => aligner.with_terminal_stop(parse_terminal_stop(value.extract()?)?),
+            "start_codons" => aligner.with_start_codons(parse_start_codons(value)?),
+            "ambiguity" => aligner.with_ambiguity(parse_ambiguity(value.extract()?)?),
+            "internal_stops" => {
+                aligner.with_internal_stops(parse_internal_stops(value.extract()?)?)
+            }
+            "drop_terminal_stop" => aligner.with_terminal_stop_dropped(value.extract()?),
+            "frameshifts" => aligner.with_frameshifts(parse_frameshifts(value)?),
+            "mask_mismatches" => {
+                masking = parse_mask_mismatches(value)?;
+                aligner
+            }
+            "mismatch_limit" => {
+                mismatch_limit = value.extract()?;
+                aligner
+            }
+            other => {
+                return Err(PyTypeError::new_err(format!(
+                    "unexpected keyword argument '{}'",
+                    other
+                )))
+            }
+        };
+    }
+    if mismatch_limit.is_some() && masking.is_none() {
+        return Err(PyValueError::new_err(
+            "mismatch_limit requires mask_mismatches",
+        ));
+    }
+    let masking = masking.map(|masking| MismatchMasking {
+        limit: mismatch_limit,
+        ..masking
+    });
+    Ok(aligner.with_mismatch_masking(masking))
+}
+
+fn parse_internal_stops(internal_stops: &str) -> PyResult<InternalStops> {
+    match internal_stops {
+        "keep" => Ok(InternalStops::Keep),
+        "mask" => Ok(InternalStops::Mask),
+        "gap" => Ok(InternalStops::Gap),
+        other => Err(PyValueError::new_err(format!(
+            "unknown internal_stops '{}'; expected keep, mask or gap",
+            other
+        ))),
+    }
+}
+
+fn parse_mask_mismatches(value: &PyAny) -> PyResult<Option<MismatchMasking>> {
+    if value.is_none() {
+        return Ok(None);
+    }
+    if let Ok(enabled) = value.extract::<bool>() {
+        return Ok(enabled.then(MismatchMasking::default));
+    }
+    let mask: &str = value.extract()?;
+    match MismatchMasking::with_mask(mask) {
+        Some(masking) => Ok(Some(masking)),
+        None => Err(PyValueError::new_err(format!(
+            "mask_mismatches expects True or three characters, got '{}'",
+            mask
+        ))),
+    }
+}
+
+fn parse_frameshifts(value: &PyAny) -> PyResult<Option<FrameshiftAlignment>> {
+    if value.is_none() {
+        return Ok(None);
+    }
+    if let Ok(enabled) = value.extract::<bool>() {
+        return Ok(enabled.then(FrameshiftAlignment::default));
+    }
+    let penalty = value
+        .extract::<u32>()
+        .ok()
+        .filter(|penalty| *penalty >= 1)
+        .ok_or_else(|| {
+            PyValueError::new_err("frameshifts expects a bool or an int penalty of at least 1")
+        })?;
+    Ok(Some(FrameshiftAlignment {
+        penalty,
+        ..FrameshiftAlignment::default()
+    }))
+}
+
+fn parse_terminal_stop(terminal_stop: &str) -> PyResult<TerminalStop> {
+    match terminal_stop {
+        "reject" => Ok(TerminalStop::Reject),
+        "strip" => Ok(TerminalStop::Strip),
+        "keep" => Ok(TerminalStop::Keep),
+        other => Err(PyValueError::new_err(format!(
+            "unknown terminal_stop '{}'; expected reject, strip or keep",
+            other
+        ))),
+    }
+}
+
+fn parse_ambiguity(ambiguity: &str) -> PyResult<Ambiguity> {
+    match ambiguity {
+        "any" => Ok(Ambiguity::Any),
+        "all" => Ok(Ambiguity::All),
+        "reject" => Ok(Ambiguity::Reject),
+        other => Err(PyValueError::new_err(format!(
+            "unknown ambiguity '{}'; expected any, all or reject",
+            other
+        ))),
+    }
+}
+
+fn parse_start_codons(value: &PyAny) -> PyResult<StartCodons> {
+    if value.is_none() {
+        return Ok(StartCodons::Off);
+    }
+    if let Ok(enabled) = value.extract::<bool>() {
+        return Ok(if enabled {
+            StartCodons::Table
+        } else {
+            StartCodons::Off
+        });
+    }
+    if let Ok(mode) = value.extract::<&str>() {
+        return match mode {
+            "table" => Ok(StartCodons::Table),
+            "off" => Ok(StartCodons::Off),
+            other => Err(PyValueError::new_err(format!(
+                "unknown start_codons '{}'; expected table, off or a list of codons",
+                other
+            ))),
+        };
+    }
+    let codons: Vec<String> = value.extract()?;
+    if let Some(bad) = codons.iter().find(|codon| codon.len() != 3) {
+        return Err(PyValueError::new_err(format!(
+            "start codon '{}' is not a triplet",
+            bad
+        )));
+    }
+    Ok(StartCodons::Codons(codons))
+}
+
+/// Checks the `threads` keyword argument: `None` for one worker per core,
+/// otherwise at least 1.
+fn check_threads(threads: Option<usize>) -> PyResult<Option<usize>> {
+    match threads {
+        Some(0) => Err(PyValueError::new_err("threads must be at least 1")),
+        threads => Ok(threads),
+    }
+}
+
+/// Runs `aligner` over a pair of FASTA files (plain, gzip or bgzip) with
+/// the GIL released. With `output_path` the alignment is written there and
+/// `None` is returned.
+fn align_files_py(
+    py: Python,
+    aligner: CodonAligner,
+    aa_path: &str,
+    nt_path: &str,
+    output_path: Option<&str>,
+    compression: &str,
+) -> PyResult<Option<String>> {
+    match output_path {
+        Some(output_path) => {
+            let compression = parse_compression(compression)?;
+            py.allow_threads(|| aligner.align_files_to(aa_path, nt_path, output_path, compression))
+                .map(|_| None)
+                .map_err(to_py_err)
+        }
+        None => py
+            .allow_threads(|| aligner.align_files(aa_path, nt_path))
+            .map(Some)
+            .map_err(to_py_err),
+    }
+}
+
+/// Reads `aa_path` and `nt_path` as FASTA, pairs their records and
+/// back-translates them with NCBI table `table_num`, given by number or
+/// name.
+#[pyfunction(
+    pair_by = "\"position\"",
+    id_regex = "None",
+    output_path = "None",
+    compression = "\"auto\"",
+    threads = "None",
+    options = "**"
+)]
+#[allow(clippy::too_many_arguments)]
+fn pn2codon_files(
+    py: Python,
+    aa_path: String,
+    nt_path: String,
+    table_num: TableKey,
+    pair_by: &str,
+    id_regex: Option<&str>,
+    output_path: Option<&str>,
+    compression: &str,
+    threads: Option<usize>,
+    options: Option<&PyDict>,
+) -> PyResult<Option<String>> {
+    let pairing = parse_pairing(pair_by, id_regex)?;
+    let aligner = table_num
+        .aligner()?
+        .with_pairing(pairing)
+        .with_threads(check_threads(threads)?)
+        .map_err(to_py_err)?;
+    let aligner = configure(aligner, options)?;
+    align_files_py(py, aligner, &aa_path, &nt_path, output_path, compression)
+}
+
+/// `pn2codon_files` with a custom codon table.
+#[pyfunction(
+    pair_by = "\"position\"",
+    id_regex = "None",
+    output_path = "None",
+    compression = "\"auto\"",
+    threads = "None",
+    options = "**"
+)]
+#[allow(clippy::too_many_arguments)]
+fn pn2codon_files_original_args(
+    py: Python,
+    aa_path: String,
+    nt_path: String,
+    gene_table: GeneTableArg,
+    pair_by: &str,
+    id_regex: Option<&str>,
+    output_path: Option<&str>,
+    compression: &str,
+    threads: Option<usize>,
+    options: Option<&PyDict>,
+) -> PyResult<Option<String>> {
+    let pairing = parse_pairing(pair_by, id_regex)?;
+    let aligner = gene_table
+        .aligner()?
+        .with_pairing(pairing)
+        .with_threads(check_threads(threads)?)
+        .map_err(to_py_err)?;
+    let aligner = configure(aligner, options)?;
+    align_files_py(py, aligner, &aa_path, &nt_path, output_path, compression)
+}
+
+/// One `(stem, output_path, error)` tuple per batch stem; exactly one of
+/// `output_path` and `error` is `None`.
+type BatchResults = Vec<(String, Option<String>, Option<PyObject>)>;
+
+/// Runs `aligner` over every file pair of a batch, given either `aa_dir`
+/// and `nt_dir` (matched by stem) or a `manifest` TSV. Files without a
+/// partner are reported as `UnpairedFileError` entries after the stems;
+/// files sharing a stem raise `DuplicateStemError`.
+#[allow(clippy::too_many_arguments)]
+fn align_batch_py(
+    py: Python,
+    aligner: CodonAligner,
+    out_dir: &str,
+    aa_dir: Option<&str>,
+    nt_dir: Option<&str>,
+    manifest: Option<&str>,
+    threads: Option<usize>,
+    compression: &str,
+) -> PyResult<BatchResults> {
+    let (jobs, unpaired) = match (aa_dir, nt_dir, manifest) {
+        (Some(aa_dir), Some(nt_dir), None) => {
+            discover_jobs(Path::new(aa_dir), Path::new(nt_dir)).map_err(to_py_err)?
+        }
+        (None, None, Some(manifest)) => (
+            read_manifest(Path::new(manifest)).map_err(to_py_err)?,
+            Vec::new(),
+        ),
+        _ => {
+            return Err(PyValueError::new_err(
+                "pass either aa_dir and nt_dir, or manifest",
+            ))
+        }
+    };
+    let options = BatchOptions {
+        threads: check_threads(threads)?,
+        compression: parse_compression(compression)?,
+    };
+    let summary = py
+        .allow_threads(|| aligner.align_batch(&jobs, Path::new(out_dir), options))
+        .map_err(to_py_err)?;
+
+    let mut results: BatchResults = summary
+        .outcomes
+        .into_iter()
+        .map(|outcome| match outcome.result {
+            Ok(path) => (outcome.stem, Some(path.display().to_string()), None),
+            Err(err) => (outcome.stem, None, Some(to_py_err(err).value(py).into())),
+        })
+        .collect();
+    results.extend(unpaired.into_iter().map(|err| {
+        let stem = match &err {
+            RustError::UnpairedFile { stem, .. } => stem.clone(),
+            _ => String::new(),
+        };
+        (stem, None, Some(to_py_err(err).value(py).into()))
+    }));
+    Ok(results)
+}
+
+/// Back-translates many gene alignments in parallel with NCBI table
+/// `table_num`, writing `<stem>.fa` (or `<stem>.fa.gz` with
+/// `compression="gzip"`) per stem into `out_dir`. Returns one
+/// `(stem, output_path, error)` tuple per stem; a failing stem does not stop
+/// the others.
+#[pyfunction(
+    aa_dir = "None",
+    nt_dir = "None",
+    manifest = "None",
+    threads = "None",
+    pair_by = "\"position\"",
+    id_regex = "None",
+    compression = "\"auto\"",
+    options = "**"
+)]
+#[allow(clippy::too_many_arguments)]
+fn pn2codon_batch(
+    py: Python,
+    out_dir: &str,
+    table_num: TableKey,
+    aa_dir: Option<&str>,
+    nt_dir: Option<&str>,
+    manifest: Option<&str>,
+    threads: Option<usize>,
+    pair_by: &str,
+    id_regex: Option<&str>,
+    compression: &str,
+    options: Option<&PyDict>,
+) -> PyResult<BatchResults> {
+    let pairing = parse_pairing(pair_by, id_regex)?;
+    let aligner = table_num.aligner()?.with_pairing(pairing);
+    let aligner = configure(aligner, options)?;
+    align_batch_py(
+        py,
+        aligner,
+        out_dir,
+        aa_dir,
+        nt_dir,
+        manifest,
+        threads,
+        compression,
+    )
+}
+
+/// `pn2codon_batch` with a custom codon table.
+#[pyfunction(
+    aa_dir = "None",
+    nt_dir = "None",
+    manifest = "None",
+    threads = "None",
+    pair_by = "\"position\"",
+    id_regex = "None",
+    compression = "\"auto\"",
+    options = "**"
+)]
+#[allow(clippy::too_many_arguments)]
+fn pn2codon_batch_original_args(
+    py: Python,
+    out_dir: &str,
+    gene_table: GeneTableArg,
+    aa_dir: Option<&str>,
+    nt_dir: Option<&str>,
+    manifest: Option<&str>,
+    threads: Option<usize>,
+    pair_by: &str,
+    id_regex: Option<&str>,
+    compression: &str,
+    options: Option<&PyDict>,
+) -> PyResult<BatchResults> {
+    let pairing = parse_pairing(pair_by, id_regex)?;
+    let aligner = gene_table.aligner()?.with_pairing(pairing);
+    let aligner = configure(aligner, options)?;
+    align_batch_py(
+        py,
+        aligner,
+        out_dir,
+        aa_dir,
+        nt_dir,
+        manifest,
+        threads,
+        compression,
+    )
+}
+
+fn signature_records(seqs: SignatureSeqs) -> impl Iterator<Item = PairedRecord> {
+    seqs.0
+        .into_iter()
+        .map(|(header, ((aa_header, aa), (_, nt_header, nt)))| {
+            PairedRecord::new(header, aa_header, aa, nt_header, nt)
+        })
+}
+
+fn table_records(seqs: TableSeqs) -> impl Iterator<Item = PairedRecord> {
+    seqs.0
+        .into_iter()
+        .map(|(header, ((aa_header, aa), (nt_header, nt)))| {
+            PairedRecord::new(header, aa_header, aa, nt_header, nt)
+        })
+}
+
+fn report_to_py(py: Python, report: AlignmentReport) -> (String, Vec<PyObject>) {
+    let fasta = report.to_fasta();
+    let errors = report
+        .failures
+        .into_iter()
+        .flat_map(|failure| failure.errors)
+        .map(|err| to_py_err(err).value(py).into())
+        .collect();
+    (fasta, errors)
+}
+
+/// Parses the keyword arguments of `translate`/`translate_batch`. `partial`
+/// is `ignore` (drop a trailing partial codon) or `mark` (translate it as
+/// `X`).
+fn translate_options(stop_symbol: char, partial: &str) -> PyResult<TranslateOptions> {
+    let partial_codon = match partial {
+        "ignore" => PartialCodon::Ignore,
+        "mark" => PartialCodon::Mark,
+        other => {
+            return Err(PyValueError::new_err(format!(
+                "unknown partial '{}'; expected ignore or mark",
+                other
+            )))
+        }
+    };
+    Ok(TranslateOptions {
+        stop_symbol,
+        partial_codon,
+    })
+}
+
+/// An NCBI genetic code, built in or loaded from a file.
+#[pyclass(name = "GeneticCode", module = "pr2codon")]
+#[derive(Clone)]
+struct PyGeneticCode {
+    code: CustomGeneticCode,
+}
+
+#[pymethods]
+impl PyGeneticCode {
+    /// NCBI table number; `None` for loaded codes that have none.
+    #[getter]
+    fn id(&self) -> Option<i32> {
+        self.code.id
+    }
+
+    #[getter]
+    fn name(&self) -> Option<String> {
+        self.code.name.clone()
+    }
+
+    #[getter]
+    fn short_name(&self) -> Option<String> {
+        self.code.short_name.clone()
+    }
+
+    /// Every name accepted as `table=`, in snake_case.
+    #[getter]
+    fn names(&self) -> Vec<String> {
+        self.code.names()
+    }
+
+    /// The 64-character amino-acid string, codons in `TCAG` order.
+    #[getter]
+    fn amino_acids(&self) -> String {
+        self.code.amino_acids.clone()
+    }
+
+    /// `M` at each initiation codon, `-` elsewhere, codons in `TCAG` order.
+    #[getter]
+    fn starts(&self) -> String {
+        self.code.starts.clone()
+    }
+
+    #[getter]
+    fn start_codons(&self) -> Vec<String> {
+        self.code.start_codons()
+    }
+
+    #[getter]
+    fn stop_codons(&self) -> Vec<String> {
+        self.code.stop_codons()
+    }
+
+    /// The code as an amino acid to codons dict, stops under `*`.
+    fn gene_table(&self) -> GeneTable {
+        self.code.gene_table()
+    }
+
+    fn __repr__(&self) -> String {
+        match (self.code.id, &self.code.name) {
+            (Some(id), Some(name)) => format!("GeneticCode({}, {:?})", id, name),
+            (Some(id), None) => format!("GeneticCode({})", id),
+            (None, Some(name)) => format!("GeneticCode({:?})", name),
+            (None, None) => "GeneticCode()".to_string(),
+        }
+    }
+}
+
+/// The outcome of `validate_gene_table`.
+#[pyclass(name = "GeneTableReport", module = "pr2codon")]
+struct PyGeneTableReport {
+    report: GeneTableReport,
+}
+
+#[pymethods]
+impl PyGeneTableReport {
+    /// `True` when the table has no errors; warnings are allowed.
+    #[getter]
+    fn valid(&self) -> bool {
+        self.report.is_valid()
+    }
+
+    #[getter]
+    fn errors(&self) -> Vec<String> {
+        self.report.errors().map(ToString::to_string).collect()
+    }
+
+    #[getter]
+    fn warnings(&self) -> Vec<String> {
+        self.report.warnings().map(ToString::to_string).collect()
+    }
+
+    /// The table with codons uppercased, `U` read as `T` and repeats dropped.
+    #[getter]
+    fn normalized(&self) -> GeneTable {
+        self.report.normalized.clone()
+    }
+
+    /// The 64-character amino-acid string of a valid table listing every
+    /// codon, else `None`.
+    #[getter]
+    fn signature(&self) -> Option<String> {
+        self.report.signature()
+    }
+
+    /// The table as a `GeneticCode` when `signature` exists, else `None`.
+    fn genetic_code(&self) -> Option<PyGeneticCode> {
+        self.report
+            .genetic_code()
+            .map(|code| PyGeneticCode { code })
+    }
+
+    fn __str__(&self) -> String {
+        self.report.to_string()
+    }
+}
+
+/// Checks an amino acid to codons dict: malformed codons, codons under two
+/// amino acids and non-amino-acid keys are errors; lowercase, `U`, repeats
+/// and unlisted codons are warnings.
+#[pyfunction]
+fn validate_gene_table(gene_table: GeneTable) -> PyGeneTableReport {
+    PyGeneTableReport {
+        report: crate::validate_gene_table(&gene_table),
+    }
+}
+
+/// Every built-in genetic code, by NCBI number.
+#[pyfunction]
+fn genetic_codes() -> Vec<PyGeneticCode> {
+    GENETIC_CODES
+        .iter()
+        .map(|code| PyGeneticCode { code: code.into() })
+        .collect()
+}
+
+/// Looks up a built-in genetic code by NCBI number or name; raises
+/// `InvalidTableError` when there is none.
+#[pyfunction]
+fn genetic_code(table: TableKey) -> PyResult<PyGeneticCode> {
+    table.genetic_code().map(|code| PyGeneticCode { code })
+}
+
+/// Reads every genetic code in an NCBI `gc.prt` or JSON file. Each of the
+/// 64 codons must be assigned exactly once; raises `InvalidGeneTableError`
+/// otherwise.
+#[pyfunction]
+fn load_genetic_codes(path: &str) -> PyResult<Vec<PyGeneticCode>> {
+    read_genetic_codes(path)
+        .map(|codes| {
+            codes
+                .into_iter()
+                .map(|code| PyGeneticCode { code })
+                .collect()
+        })
+        .map_err(to_py_err)
+}
+
+/// Reads one genetic code from an NCBI `gc.prt` or JSON file. `table`
+/// picks a code by id or name when the file holds several.
+#[pyfunction(table = "None")]
+fn load_genetic_code(path: &str, table: Option<&PyAny>) -> PyResult<PyGeneticCode> {
+    let key = table
+        .map(|table| table.str()?.extract::<String>())
+        .transpose()?;
+    read_genetic_codes(path)
+        .and_then(|codes| select_genetic_code(codes, key.as_deref(), path))
+        .map(|code| PyGeneticCode { code })
+        .map_err(to_py_err)
+}
+
+/// Translates a nucleotide sequence with NCBI table `table`. Ambiguous
+/// codons become the amino acid all their expansions agree on, or `X`.
+#[pyfunction(
+    table = "TableKey::Number(1)",
+    stop_symbol = "'*'",
+    partial = "\"ignore\""
+)]
+fn translate(nt: &str, table: TableKey, stop_symbol: char, partial: &str) -> PyResult<String> {
+    let options = translate_options(stop_symbol, partial)?;
+    let code = table.genetic_code()?;
+    Ok(translate_with_signature(nt, &code.amino_acids, &options))
+}
+
+/// `translate` over a list of sequences, in parallel with the GIL released.
+#[pyfunction(
+    table = "TableKey::Number(1)",
+    stop_symbol = "'*'",
+    partial = "\"ignore\""
+)]
+fn translate_batch(
+    py: Python,
+    seqs: Vec<String>,
+    table: TableKey,
+    stop_symbol: char,
+    partial: &str,
+) -> PyResult<Vec<String>> {
+    let options = translate_options(stop_symbol, partial)?;
+    let code = table.genetic_code()?;
+    Ok(py.allow_threads(|| translate_batch_with_signature(&seqs, &code.amino_acids, &options)))
+}
+
+#[pyfunction]
+fn attempt_iupac_substitution(original_triplet: &str, taxa: Vec<String>) -> Option<String> {
+    crate::attempt_iupac_substitution(original_triplet, taxa)
+}
+
+/// Records are translated across `threads` worker threads (one per core by
+/// default) with the GIL released.
+#[pyfunction(threads = "None", options = "**")]
+#[allow(clippy::too_many_arguments)]
+fn pn2codon(
+    py: Python,
+    _file_steem: String,
+    aa_path: String,
+    nt_path: String,
+    table_num: TableKey,
+    seqs: SignatureSeqs,
+    threads: Option<usize>,
+    options: Option<&PyDict>,
+) -> PyResult<String> {
+    let aligner = table_num
+        .aligner()?
+        .with_source_paths(&aa_path, &nt_path)
+        .with_threads(check_threads(threads)?)
+        .map_err(to_py_err)?;
+    let aligner = configure(aligner, options)?;
+    py.allow_threads(|| aligner.align(signature_records(seqs)))
+        .map_err(to_py_err)
+}
+
+/// Like `pn2codon`, but processes every record and returns
+/// `(codon_fasta, errors)` where `errors` lists one exception instance (not
+/// raised) per problem found.
+#[pyfunction(threads = "None", options = "**")]
+#[allow(clippy::too_many_arguments)]
+fn pn2codon_collect(
+    py: Python,
+    _file_steem: String,
+    aa_path: String,
+    nt_path: String,
+    table_num: TableKey,
+    seqs: SignatureSeqs,
+    threads: Option<usize>,
+    options: Option<&PyDict>,
+) -> PyResult<(String, Vec<PyObject>)> {
+    let aligner = table_num
+        .aligner()?
+        .with_source_paths(&aa_path, &nt_path)
+        .with_threads(check_threads(threads)?)
+        .map_err(to_py_err)?;
+    let aligner = configure(aligner, options)?;
+    let report = py.allow_threads(|| aligner.align_all(signature_records(seqs)));
+    Ok(report_to_py(py, report))
+}
+
+#[pyfunction(threads = "None", options = "**")]
+#[allow(clippy::too_many_arguments)]
+fn pn2codon_original_args(
+    py: Python,
+    _file_steem: String,
+    aa_path: String,
+    nt_path: String,
+    gene_table: GeneTableArg,
+    seqs: TableSeqs,
+    threads: Option<usize>,
+    options: Option<&PyDict>,
+) -> PyResult<String> {
+    let aligner = gene_table
+        .aligner()?
+        .with_source_paths(&aa_path, &nt_path)
+        .with_threads(check_threads(threads)?)
+        .map_err(to_py_err)?;
+    let aligner = configure(aligner, options)?;
+    py.allow_threads(|| aligner.align(table_records(seqs)))
+        .map_err(to_py_err)
+}
+
+/// Like `pn2codon_original_args`, but processes every record and returns
+/// `(codon_fasta, errors)`.
+#[pyfunction(threads = "None", options = "**")]
+#[allow(clippy::too_many_arguments)]
+fn pn2codon_original_args_collect(
+    py: Python,
+    _file_steem: String,
+    aa_path: String,
+    nt_path: String,
+    gene_table: GeneTableArg,
+    seqs: TableSeqs,
+    threads: Option<usize>,
+    options: Option<&PyDict>,
+) -> PyResult<(String, Vec<PyObject>)> {
+    let aligner = gene_table
+        .aligner()?
+        .with_source_paths(&aa_path, &nt_path)
+        .with_threads(check_threads(threads)?)
+        .map_err(to_py_err)?;
+    let aligner = configure(aligner, options)?;
+    let report = py.allow_threads(|| aligner.align_all(table_records(seqs)));
+    Ok(report_to_py(py, report))
+}
+
+/// A back-translated record with what was learned about it.
+#[pyclass(module = "pr2codon")]
+struct CodonRecord {
+    #[pyo3(get)]
+    header: String,
+    #[pyo3(get)]
+    sequence: String,
+    /// Chosen frame (`+1` to `-3`) with `detect_frame`, else `None`.
+    #[pyo3(get)]
+    frame: Option<String>,
+    /// `(start, end)` of the nucleotide record kept by `trim_flanks`,
+    /// 0-based and end-exclusive, else `None`.
+    #[pyo3(get)]
+    coding_region: Option<(usize, usize)>,
+    /// The accepted terminal stop codon with `terminal_stop`, else `None`.
+    #[pyo3(get)]
+    terminal_stop: Option<String>,
+    /// The alternative initiation codon accepted with `start_codons`, else
+    /// `None`.
+    #[pyo3(get)]
+    alternative_start: Option<String>,
+    /// How many codons were accepted only through IUPAC ambiguity codes.
+    #[pyo3(get)]
+    ambiguous_codons: usize,
+    /// `(alignment_position, nt_offset, expected_aa, codon, details)` of
+    /// each codon replaced with `mask_mismatches`; positions are 1-based
+    /// alignment columns, offsets 0-based.
+    #[pyo3(get)]
+    masked_codons: Vec<(usize, usize, char, String, String)>,
+    /// How many stop codons the record has.
+    #[pyo3(get)]
+    stop_codons: usize,
+    /// `(alignment_position, nt_offset, shift, inserted)` of each shift
+    /// placed with `frameshifts`: `shift` is negative for missing bases and
+    /// positive for extra ones, which `inserted` holds.
+    #[pyo3(get)]
+    frameshifts: Vec<(usize, usize, isize, String)>,
+}
+
+impl From<crate::CodonRecord> for CodonRecord {
+    fn from(record: crate::CodonRecord) -> Self {
+        Self {
+            header: record.header,
+            sequence: record.sequence,
+            frame: record.metadata.frame.map(|frame| frame.to_string()),
+            coding_region: record
+                .metadata
+                .coding_region
+                .map(|region| (region.start, region.end)),
+            terminal_stop: record.metadata.terminal_stop,
+            alternative_start: record.metadata.alternative_start,
+            ambiguous_codons: record.metadata.ambiguous_codons,
+            masked_codons: record
+                .metadata
+                .masked_codons
+                .into_iter()
+                .map(|masked| {
+                    (
+                        masked.alignment_position,
+                        masked.nt_offset,
+                        masked.expected_aa,
+                        masked.codon,
+                        masked.details,
+                    )
+                })
+                .collect(),
+            stop_codons: record.metadata.stop_codons,
+            frameshifts: record
+                .metadata
+                .frameshifts
+                .into_iter()
+                .map(|frameshift| {
+                    let shift = frameshift.shift();
+                    let inserted = match frameshift.kind {
+                        FrameshiftKind::Deletion { .. } => String::new(),
+                        FrameshiftKind::Insertion { bases } => bases,
+                    };
+                    (
+                        frameshift.alignment_position,
+                        frameshift.nt_offset,
+                        shift,
+                        inserted,
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Yields `(header, codon_seq)` one record at a time, or `CodonRecord`
+/// objects when created with `detailed=True`.
+///
+/// A failing record raises its exception from `__next__`; iterating again
+/// continues with the next record.
+#[pyclass(module = "pr2codon")]
+struct CodonRecordIterator {
+    stream: CodonStream<std::vec::IntoIter<PairedRecord>>,
+    detailed: bool,
+}
+
+#[pymethods]
+impl CodonRecordIterator {
+    fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __next__(mut slf: PyRefMut<Self>, py: Python) -> PyResult<Option<PyObject>> {
+        let detailed = slf.detailed;
+        let stream = &mut slf.stream;
+        match py.allow_threads(|| stream.next()) {
+            None => Ok(None),
+            Some(Ok(record)) if detailed => Ok(Some(CodonRecord::from(record).into_py(py))),
+            Some(Ok(record)) => Ok(Some((record.header, record.sequence).into_py(py))),
+            Some(Err(mut failure)) => Err(to_py_err(failure.errors.swap_remove(0))),
+        }
+    }
+
+    /// Writes the remaining records as FASTA to `handle`, any object with a
+    /// `write(str)` method, one record at a time. Raises at the first
+    /// failing record; returns the number of records written.
+    fn write_to(&mut self, py: Python, handle: &PyAny) -> PyResult<usize> {
+        let mut written = 0;
+        loop {
+            let stream = &mut self.stream;
+            match py.allow_threads(|| stream.next()) {
+                None => return Ok(written),
+                Some(Ok(record)) => {
+                    handle.call_method1(
+                        "write",
+                        (format!("{}\n{}\n", record.header, record.sequence),),
+                    )?;
+                    written += 1;
+                }
+                Some(Err(mut failure)) => return Err(to_py_err(failure.errors.swap_remove(0))),
+            }
+        }
+    }
+}
+
+/// Streaming `pn2codon`: returns a `CodonRecordIterator` instead of one
+/// FASTA string.
+#[pyfunction(detailed = "false", options = "**")]
+fn pn2codon_iter(
+    _file_steem: String,
+    aa_path: String,
+    nt_path: String,
+    table_num: TableKey,
+    seqs: SignatureSeqs,
+    detailed: bool,
+    options: Option<&PyDict>,
+) -> PyResult<CodonRecordIterator> {
+    let aligner = table_num.aligner()?.with_source_paths(&aa_path, &nt_path);
+    let aligner = configure(aligner, options)?;
+    let records: Vec<PairedRecord> = signature_records(seqs).collect();
+    Ok(CodonRecordIterator {
+        stream: aligner.stream(records),
+        detailed,
+    })
+}
+
+/// Streaming `pn2codon_original_args`.
+#[pyfunction(detailed = "false", options = "**")]
+fn pn2codon_original_args_iter(
+    _file_steem: String,
+    aa_path: String,
+    nt_path: String,
+    gene_table: GeneTableArg,
+    seqs: TableSeqs,
+    detailed: bool,
+    options: Option<&PyDict>,
+) -> PyResult<CodonRecordIterator> {
+    let aligner = gene_table.aligner()?.with_source_paths(&aa_path, &nt_path);
+    let aligner = configure(aligner, options)?;
+    let records: Vec<PairedRecord> = table_records(seqs).collect();
+    Ok(CodonRecordIterator {
+        stream: aligner.stream(records),
+        detailed,
+    })
+}
+
+/// Streaming `pn2codon_files`. Only the output is streamed: both inputs
+/// are read and paired in full before the first record is yielded, and
+/// records are translated as the iterator advances.
+#[pyfunction(
+    pair_by = "\"position\"",
+    id_regex = "None",
+    detailed = "false",
+    options = "**"
+)]
+#[allow(clippy::too_many_arguments)]
+fn pn2codon_files_iter(
+    py: Python,
+    aa_path: String,
+    nt_path: String,
+    table_num: TableKey,
+    pair_by: &str,
+    id_regex: Option<&str>,
+    detailed: bool,
+    options: Option<&PyDict>,
+) -> PyResult<CodonRecordIterator> {
+    let pairing = parse_pairing(pair_by, id_regex)?;
+    let aligner = table_num.aligner()?.with_pairing(pairing);
+    let aligner = configure(aligner, options)?;
+    py.allow_threads(|| aligner.stream_files(&aa_path, &nt_path))
+        .map(|stream| CodonRecordIterator { stream, detailed })
+        .map_err(to_py_err)
+}
+
+/// Streaming `pn2codon_files_original_args`.
+#[pyfunction(
+    pair_by = "\"position\"",
+    id_regex = "None",
+    detailed = "false",
+    options = "**"
+)]
+#[allow(clippy::too_many_arguments)]
+fn pn2codon_files_original_args_iter(
+    py: Python,
+    aa_path: String,
+    nt_path: String,
+    gene_table: GeneTableArg,
+    pair_by: &str,
+    id_regex: Option<&str>,
+    detailed: bool,
+    options: Option<&PyDict>,
+) -> PyResult<CodonRecordIterator> {
+    let pairing = parse_pairing(pair_by, id_regex)?;
+    let aligner = gene_table.aligner()?.with_pairing(pairing);
+    let aligner = configure(aligner, options)?;
+    py.allow_threads(|| aligner.stream_files(&aa_path, &nt_path))
+        .map(|stream| CodonRecordIterator { stream, detailed })
+        .map_err(to_py_err)
+}
+
+#[pymodule]
+fn pr2codon(py: Python, m: &PyModule) -> PyResult<()> {
+    m.add("Pn2CodonError", py.get_type::<Pn2CodonError>())?;
+    m.add_class::<CodonRecord>()?;
+    m.add_class::<CodonRecordIterator>()?;
+    m.add_class::<PyGeneticCode>()?;
+    m.add_class::<PyGeneTableReport>()?;
+    m.add("InvalidTableError", py.get_type::<InvalidTableError>())?;
+    m.add(
+        "InvalidGeneTableError",
+        py.get_type::<InvalidGeneTableError>(),
+    )?;
+    m.add("HeaderMismatchError", py.get_type::<HeaderMismatchError>())?;
+    m.add("LengthMismatchError", py.get_type::<LengthMismatchError>())?;
+    m.add(
+        "MissingAminoAcidError",
+        py.get_type::<MissingAminoAcidError>(),
+    )?;
+    m.add("CodonMismatchError", py.get_type::<CodonMismatchError>())?;
+    m.add("FastaReadError", py.get_type::<FastaReadError>())?;
+    m.add("FastaFormatError", py.get_type::<FastaFormatError>())?;
+    m.add("OutputWriteError", py.get_type::<OutputWriteError>())?;
+    m.add(
+        "RecordCountMismatchError",
+        py.get_type::<RecordCountMismatchError>(),
+    )?;
+    m.add(
+        "InvalidIdPatternError",
+        py.get_type::<InvalidIdPatternError>(),
+    )?;
+    m.add("PairingError", py.get_type::<PairingError>())?;
+    m.add("UnpairedFileError", py.get_type::<UnpairedFileError>())?;
+    m.add("DuplicateStemError", py.get_type::<DuplicateStemError>())?;
+    m.add("ManifestFormatError", py.get_type::<ManifestFormatError>())?;
+    m.add_function(wrap_pyfunction!(genetic_codes, m)?)?;
+    m.add_function(wrap_pyfunction!(genetic_code, m)?)?;
+    m.add_function(wrap_pyfunction!(load_genetic_codes, m)?)?;
+    m.add_function(wrap_pyfunction!(load_genetic_code, m)?)?;
+    m.add_function(wrap_pyfunction!(validate_gene_table, m)?)?;
+    m.add_function(wrap_pyfunction!(pn2codon, m)?)?;
+    m.add_function(wrap_pyfunction!(pn2codon_original_args, m)?)?;
+    m.add_function(wrap_pyfunction!(pn2codon_collect, m)?)?;
+    m.add_function(wrap_pyfunction!(pn2codon_original_args_collect, m)?)?;
+    m.add_function(wrap_pyfunction!(pn2codon_files, m)?)?;
+    m.add_function(wrap_pyfunction!(pn2codon_files_original_args, m)?)?;
+    m.add_function(wrap_pyfunction!(pn2codon_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(pn2codon_iter, m)?)?;
+    m.add_function(wrap_pyfunction!(pn2codon_original_args_iter, m)?)?;
+    m.add_function(wrap_pyfunction!(pn2codon_files_iter, m)?)?;
+    m.add_function(wrap_pyfunction!(pn2codon_files_original_args_iter, m)?)?;
+    m.add_function(wrap_pyfunction!(pn2codon_batch_original_args, m)?)?;
+    m.add_function(wrap_pyfunction!(attempt_iupac_substitution, m)?)?;
+    m.add_function(wrap_pyfunction!(translate, m)?)?;
+    m.add_function(wrap_pyfunction!(translate_batch, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,382 @@
+//! The NCBI genetic codes built into the crate.
+//!
+//! Each code can be looked up by its NCBI number or by name; see
+//! [`genetic_code`] and [`genetic_code_by_name`].
+
+/// One NCBI translation table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GeneticCode {
+    /// NCBI table number (`transl_table`).
+    pub id: i32,
+    /// Full NCBI name; codes shared by several lineages list them joined by
+    /// `; `.
+    pub name: &'static str,
+    /// NCBI short name (`SGC0` to `SGC9`), where NCBI assigns one.
+    pub short_name: Option<&'static str>,
+    /// Further snake_case names accepted by [`genetic_code_by_name`].
+    pub aliases: &'static [&'static str],
+    /// The amino acid of each codon, in `TCAG` order, `*` for stops.
+    pub amino_acids: &'static str,
+    /// `M` at each codon that can start translation, `-` elsewhere.
+    pub starts: &'static str,
+}
+
+impl GeneticCode {
+    /// Every name this code answers to: the full name (and each lineage of
+    /// a joined name) in snake_case, the short name and the aliases.
+    pub fn names(&self) -> Vec<String> {
+        code_names(
+            Some(self.name),
+            self.short_name,
+            self.aliases.iter().copied(),
+        )
+    }
+
+    /// The codons that can start translation, in `TCAG` order.
+    pub fn start_codons(&self) -> Vec<String> {
+        codons_marked(self.starts, b'M')
+    }
+
+    /// The stop codons, in `TCAG` order.
+    pub fn stop_codons(&self) -> Vec<String> {
+        codons_marked(self.amino_acids, b'*')
+    }
+}
+
+pub(crate) fn code_names<'a>(
+    name: Option<&str>,
+    short_name: Option<&str>,
+    aliases: impl Iterator<Item = &'a str>,
+) -> Vec<String> {
+    let mut names: Vec<String> = name.into_iter().map(normalize_name).collect();
+    if let Some(name) = name.filter(|name| name.contains(';')) {
+        names.extend(name.split(';').map(normalize_name));
+    }
+    names.extend(short_name.map(normalize_name));
+    names.extend(aliases.map(normalize_name));
+    names.dedup();
+    names
+}
+
+/// The codons whose position in a 64-character table string holds `mark`.
+pub(crate) fn codons_marked(table: &str, mark: u8) -> Vec<String> {
+    table
+        .bytes()
+        .enumerate()
+        .filter(|(_, symbol)| *symbol == mark)
+        .map(|(index, _)| codon_at(index))
+        .collect()
+}
+
+/// The codon at `index` of a 64-character table string.
+pub(crate) fn codon_at(index: usize) -> String {
+    const BASES: [char; 4] = ['T', 'C', 'A', 'G'];
+    [index / 16, (index / 4) % 4, index % 4]
+        .iter()
+        .map(|base| BASES[*base])
+        .collect()
+}
+
+/// Lowercase with every run of other characters turned into `_`, so
+/// `"Vertebrate Mitochondrial"`, `"vertebrate-mitochondrial"` and
+/// `"vertebrate_mitochondrial"` compare equal.
+pub(crate) fn normalize_name(name: &str) -> String {
+    let mut normalized = String::with_capacity(name.len());
+    for c in name.trim().chars() {
+        if c.is_ascii_alphanumeric() {
+            normalized.push(c.to_ascii_lowercase());
+        } else if !normalized.is_empty() && !normalized.ends_with('_') {
+            normalized.push('_');
+        }
+    }
+    if normalized.ends_with('_') {
+        normalized.pop();
+    }
+    normalized
+}
+
+/// Every built-in genetic code, by NCBI number.
+pub const GENETIC_CODES: &[GeneticCode] = &[
+    GeneticCode {
+        id: 1,
+        name: "Standard",
+        short_name: Some("SGC0"),
+        aliases: &["universal"],
+        amino_acids: "FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "---M---------------M---------------M----------------------------",
+    },
+    GeneticCode {
+        id: 2,
+        name: "Vertebrate Mitochondrial",
+        short_name: Some("SGC1"),
+        aliases: &["vertebrate_mito"],
+        amino_acids: "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSS**VVVVAAAADDEEGGGG",
+        starts: "--------------------------------MMMM---------------M------------",
+    },
+    GeneticCode {
+        id: 3,
+        name: "Yeast Mitochondrial",
+        short_name: Some("SGC2"),
+        aliases: &["yeast_mito"],
+        amino_acids: "FFLLSSSSYY**CCWWTTTTPPPPHHQQRRRRIIMMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "----------------------------------MM---------------M------------",
+    },
+    GeneticCode {
+        id: 4,
+        name: "Mold Mitochondrial; Protozoan Mitochondrial; Coelenterate Mitochondrial; Mycoplasma; Spiroplasma",
+        short_name: Some("SGC3"),
+        aliases: &["mold_mitochondrial", "protozoan_mitochondrial", "coelenterate_mitochondrial", "mycoplasma", "spiroplasma"],
+        amino_acids: "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "--MM---------------M------------MMMM---------------M------------",
+    },
+    GeneticCode {
+        id: 5,
+        name: "Invertebrate Mitochondrial",
+        short_name: Some("SGC4"),
+        aliases: &["invertebrate_mito"],
+        amino_acids: "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSSSSVVVVAAAADDEEGGGG",
+        starts: "---M----------------------------MMMM---------------M------------",
+    },
+    GeneticCode {
+        id: 6,
+        name: "Ciliate Nuclear; Dasycladacean Nuclear; Hexamita Nuclear",
+        short_name: Some("SGC5"),
+        aliases: &["ciliate_nuclear", "dasycladacean_nuclear", "hexamita_nuclear"],
+        amino_acids: "FFLLSSSSYYQQCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "-----------------------------------M----------------------------",
+    },
+    GeneticCode {
+        id: 9,
+        name: "Echinoderm Mitochondrial; Flatworm Mitochondrial",
+        short_name: Some("SGC8"),
+        aliases: &["echinoderm_mitochondrial", "flatworm_mitochondrial"],
+        amino_acids: "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNNKSSSSVVVVAAAADDEEGGGG",
+        starts: "-----------------------------------M---------------M------------",
+    },
+    GeneticCode {
+        id: 10,
+        name: "Euplotid Nuclear",
+        short_name: Some("SGC9"),
+        aliases: &[],
+        amino_acids: "FFLLSSSSYY**CCCWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "-----------------------------------M----------------------------",
+    },
+    GeneticCode {
+        id: 11,
+        name: "Bacterial, Archaeal and Plant Plastid",
+        short_name: None,
+        aliases: &["bacterial", "archaeal", "plant_plastid", "prokaryotic"],
+        amino_acids: "FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "---M---------------M------------MMMM---------------M------------",
+    },
+    GeneticCode {
+        id: 12,
+        name: "Alternative Yeast Nuclear",
+        short_name: None,
+        aliases: &[],
+        amino_acids: "FFLLSSSSYY**CC*WLLLSPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "-------------------M---------------M----------------------------",
+    },
+    GeneticCode {
+        id: 13,
+        name: "Ascidian Mitochondrial",
+        short_name: None,
+        aliases: &[],
+        amino_acids: "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSSGGVVVVAAAADDEEGGGG",
+        starts: "---M------------------------------MM---------------M------------",
+    },
+    GeneticCode {
+        id: 14,
+        name: "Alternative Flatworm Mitochondrial",
+        short_name: None,
+        aliases: &[],
+        amino_acids: "FFLLSSSSYYY*CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNNKSSSSVVVVAAAADDEEGGGG",
+        starts: "-----------------------------------M----------------------------",
+    },
+    GeneticCode {
+        id: 15,
+        name: "Blepharisma Macronuclear",
+        short_name: None,
+        aliases: &[],
+        amino_acids: "FFLLSSSSYY*QCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "-----------------------------------M----------------------------",
+    },
+    GeneticCode {
+        id: 16,
+        name: "Chlorophycean Mitochondrial",
+        short_name: None,
+        aliases: &[],
+        amino_acids: "FFLLSSSSYY*LCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "-----------------------------------M----------------------------",
+    },
+    GeneticCode {
+        id: 21,
+        name: "Trematode Mitochondrial",
+        short_name: None,
+        aliases: &[],
+        amino_acids: "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNNKSSSSVVVVAAAADDEEGGGG",
+        starts: "-----------------------------------M---------------M------------",
+    },
+    GeneticCode {
+        id: 22,
+        name: "Scenedesmus obliquus Mitochondrial",
+        short_name: None,
+        aliases: &[],
+        amino_acids: "FFLLSS*SYY*LCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "-----------------------------------M----------------------------",
+    },
+    GeneticCode {
+        id: 23,
+        name: "Thraustochytrium Mitochondrial",
+        short_name: None,
+        aliases: &[],
+        amino_acids: "FF*LSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "--------------------------------M--M---------------M------------",
+    },
+    GeneticCode {
+        id: 24,
+        name: "Rhabdopleuridae Mitochondrial",
+        short_name: None,
+        aliases: &["pterobranchia_mitochondrial"],
+        amino_acids: "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSSKVVVVAAAADDEEGGGG",
+        starts: "---M---------------M---------------M---------------M------------",
+    },
+    GeneticCode {
+        id: 25,
+        name: "Candidate Division SR1 and Gracilibacteria",
+        short_name: None,
+        aliases: &["sr1", "gracilibacteria"],
+        amino_acids: "FFLLSSSSYY**CCGWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "---M-------------------------------M---------------M------------",
+    },
+    GeneticCode {
+        id: 26,
+        name: "Pachysolen tannophilus Nuclear",
+        short_name: None,
+        aliases: &[],
+        amino_acids: "FFLLSSSSYY**CC*WLLLAPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "-------------------M---------------M----------------------------",
+    },
+    GeneticCode {
+        id: 27,
+        name: "Karyorelict Nuclear",
+        short_name: None,
+        aliases: &[],
+        amino_acids: "FFLLSSSSYYQQCCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "-----------------------------------M----------------------------",
+    },
+    GeneticCode {
+        id: 28,
+        name: "Condylostoma Nuclear",
+        short_name: None,
+        aliases: &[],
+        amino_acids: "FFLLSSSSYYQQCCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "-----------------------------------M----------------------------",
+    },
+    GeneticCode {
+        id: 29,
+        name: "Mesodinium Nuclear",
+        short_name: None,
+        aliases: &[],
+        amino_acids: "FFLLSSSSYYYYCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "-----------------------------------M----------------------------",
+    },
+    GeneticCode {
+        id: 30,
+        name: "Peritrich Nuclear",
+        short_name: None,
+        aliases: &[],
+        amino_acids: "FFLLSSSSYYEECC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "-----------------------------------M----------------------------",
+    },
+    GeneticCode {
+        id: 31,
+        name: "Blastocrithidia Nuclear",
+        short_name: None,
+        aliases: &[],
+        amino_acids: "FFLLSSSSYYEECCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "-----------------------------------M----------------------------",
+    },
+    GeneticCode {
+        id: 32,
+        name: "Balanophoraceae Plastid",
+        short_name: None,
+        aliases: &[],
+        amino_acids: "FFLLSSSSYY*WCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "---M---------------M------------MMMM---------------M------------",
+    },
+    GeneticCode {
+        id: 33,
+        name: "Cephalodiscidae Mitochondrial",
+        short_name: None,
+        aliases: &[],
+        amino_acids: "FFLLSSSSYYY*CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSSKVVVVAAAADDEEGGGG",
+        starts: "---M---------------M---------------M---------------M------------",
+    },
+];
+
+/// Looks up a built-in genetic code by NCBI number.
+pub fn genetic_code(id: i32) -> Option<&'static GeneticCode> {
+    GENETIC_CODES.iter().find(|code| code.id == id)
+}
+
+/// Looks up a built-in genetic code by name, short name or alias, ignoring
+/// case and punctuation: `"Standard"`, `"SGC1"` and
+/// `"vertebrate_mitochondrial"` all work.
+pub fn genetic_code_by_name(name: &str) -> Option<&'static GeneticCode> {
+    let wanted = normalize_name(name);
+    if wanted.is_empty() {
+        return None;
+    }
+    GENETIC_CODES
+        .iter()
+        .find(|code| code.names().contains(&wanted))
+}
+
+/// One `id: name` line per built-in code, for error messages and listings.
+pub(crate) fn describe_genetic_codes() -> String {
+    GENETIC_CODES
+        .iter()
+        .map(|code| format!("{:>2}: {}", code.id, code.name))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CodonTable, Pn2CodonError};
+
+    #[test]
+    fn numbers_names_and_aliases_find_the_same_code() {
+        let by_number = genetic_code(2).unwrap();
+        for name in [
+            "Vertebrate Mitochondrial",
+            "SGC1",
+            "sgc1",
+            "vertebrate_mitochondrial",
+            "  VERTEBRATE-mitochondrial.",
+            "vertebrate mito",
+        ] {
+            let code = genetic_code_by_name(name).unwrap_or_else(|| panic!("{}", name));
+            assert_eq!(code, by_number, "{}", name);
+        }
+    }
+
+    #[test]
+    fn unknown_names_are_reported() {
+        assert!(genetic_code_by_name("").is_none());
+        assert!(matches!(
+            CodonTable::from_table_name("martian"),
+            Err(Pn2CodonError::UnknownTableName { requested }) if requested == "martian"
+        ));
+    }
+
+    #[test]
+    fn names_are_normalized_to_snake_case() {
+        assert_eq!(
+            normalize_name(" Echinoderm & Flatworm  Mito. "),
+            "echinoderm_flatworm_mito"
+        );
+    }
+}
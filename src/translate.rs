@@ -0,0 +1,228 @@
+use rayon::prelude::*;
+
+use crate::{codon_index, fill_possible_bases, genetic_code, Pn2CodonError};
+
+/// What [`translate`] does with the one or two bases left after the last
+/// full codon.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PartialCodon {
+    /// Leave them out of the peptide.
+    #[default]
+    Ignore,
+    /// Translate them as `X`.
+    Mark,
+}
+
+/// Forward translation settings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TranslateOptions {
+    /// Symbol written for stop codons.
+    pub stop_symbol: char,
+    pub partial_codon: PartialCodon,
+}
+
+impl Default for TranslateOptions {
+    fn default() -> Self {
+        Self {
+            stop_symbol: '*',
+            partial_codon: PartialCodon::Ignore,
+        }
+    }
+}
+
+fn is_gap(base: u8) -> bool {
+    base == b'-' || base == b'.'
+}
+
+/// Translates one codon. IUPAC codes are expanded and resolve to a single
+/// amino acid only when every expansion agrees; `---` becomes `-`, and
+/// anything else that cannot be resolved becomes `X`.
+fn translate_codon(signature: &[u8], codon: &[u8], options: &TranslateOptions) -> char {
+    if codon.iter().all(|base| is_gap(*base)) {
+        return '-';
+    }
+
+    let mut possible = [[0_u8; 4]; 3];
+    let mut lens = [0_usize; 3];
+    for (i, base) in codon.iter().enumerate() {
+        lens[i] = fill_possible_bases(*base, &mut possible[i]);
+        if lens[i] == 0 {
+            return 'X';
+        }
+    }
+
+    let mut resolved = None;
+    for b0 in possible[0].iter().take(lens[0]) {
+        for b1 in possible[1].iter().take(lens[1]) {
+            for b2 in possible[2].iter().take(lens[2]) {
+                let aa = codon_index(&[*b0, *b1, *b2]).and_then(|idx| signature.get(idx));
+                match (resolved, aa) {
+                    (_, None) => return 'X',
+                    (None, Some(aa)) => resolved = Some(*aa),
+                    (Some(previous), Some(aa)) if previous != *aa => return 'X',
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    match resolved {
+        Some(b'*') => options.stop_symbol,
+        Some(aa) => char::from(aa),
+        None => 'X',
+    }
+}
+
+/// Translates `nt` with a 64-character amino-acid signature, as in
+/// [`GeneticCode::amino_acids`](crate::GeneticCode::amino_acids).
+pub fn translate_with_signature(nt: &str, signature: &str, options: &TranslateOptions) -> String {
+    let signature = signature.as_bytes();
+    let chunks = nt.as_bytes().chunks(3);
+    let mut out = String::with_capacity(chunks.len());
+    for codon in chunks {
+        if codon.len() == 3 {
+            out.push(translate_codon(signature, codon, options));
+        } else if options.partial_codon == PartialCodon::Mark {
+            out.push('X');
+        }
+    }
+    out
+}
+
+/// Translates a nucleotide sequence with NCBI table `table_num`.
+pub fn translate(
+    nt: &str,
+    table_num: i32,
+    options: &TranslateOptions,
+) -> Result<String, Pn2CodonError> {
+    let signature = genetic_code(table_num).map(|code| code.amino_acids).ok_or(
+        Pn2CodonError::InvalidTable {
+            requested: table_num,
+        },
+    )?;
+    Ok(translate_with_signature(nt, signature, options))
+}
+
+/// [`translate`] over many sequences in parallel, in input order.
+pub fn translate_batch<S>(
+    seqs: &[S],
+    table_num: i32,
+    options: &TranslateOptions,
+) -> Result<Vec<String>, Pn2CodonError>
+where
+    S: AsRef<str> + Sync,
+{
+    let signature = genetic_code(table_num).map(|code| code.amino_acids).ok_or(
+        Pn2CodonError::InvalidTable {
+            requested: table_num,
+        },
+    )?;
+    Ok(translate_batch_with_signature(seqs, signature, options))
+}
+
+/// [`translate_with_signature`] over many sequences in parallel, in input
+/// order.
+pub fn translate_batch_with_signature<S>(
+    seqs: &[S],
+    signature: &str,
+    options: &TranslateOptions,
+) -> Vec<String>
+where
+    S: AsRef<str> + Sync,
+{
+    seqs.par_iter()
+        .map(|nt| translate_with_signature(nt.as_ref(), signature, options))
+        .collect()
+}
+
+fn complement(base: u8) -> u8 {
+    match base {
+        b'A' => b'T',
+        b'T' | b'U' => b'A',
+        b'C' => b'G',
+        b'G' => b'C',
+        b'R' => b'Y',
+        b'Y' => b'R',
+        b'K' => b'M',
+        b'M' => b'K',
+        b'B' => b'V',
+        b'V' => b'B',
+        b'D' => b'H',
+        b'H' => b'D',
+        b'a' => b't',
+        b't' | b'u' => b'a',
+        b'c' => b'g',
+        b'g' => b'c',
+        b'r' => b'y',
+        b'y' => b'r',
+        b'k' => b'm',
+        b'm' => b'k',
+        b'b' => b'v',
+        b'v' => b'b',
+        b'd' => b'h',
+        b'h' => b'd',
+        other => other,
+    }
+}
+
+/// Reverse complement of a nucleotide sequence, IUPAC codes included.
+/// Case is kept; `U` complements to `A`; other symbols are left as they are.
+pub fn reverse_complement(nt: &str) -> String {
+    nt.bytes()
+        .rev()
+        .map(|base| char::from(complement(base)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn standard(nt: &str) -> String {
+        translate(nt, 1, &TranslateOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn resolves_iupac_codons_only_when_expansions_agree() {
+        // GCN is always A; YTN is L or F.
+        assert_eq!(standard("ATGGCNYTN"), "MAX");
+        assert_eq!(standard("atggcnyTR"), "MAL");
+    }
+
+    #[test]
+    fn gap_codons_become_gaps() {
+        assert_eq!(standard("ATG---AAA"), "M-K");
+        assert_eq!(standard("ATG-A-AAA"), "MXK");
+    }
+
+    #[test]
+    fn trailing_partial_codon_is_ignored_or_marked() {
+        assert_eq!(standard("ATGAAAGC"), "MK");
+        let marked = TranslateOptions {
+            partial_codon: PartialCodon::Mark,
+            ..TranslateOptions::default()
+        };
+        assert_eq!(translate("ATGAAAG", 1, &marked).unwrap(), "MKX");
+    }
+
+    #[test]
+    fn stops_use_the_stop_symbol() {
+        let options = TranslateOptions {
+            stop_symbol: '.',
+            ..TranslateOptions::default()
+        };
+        assert_eq!(translate("ATGTAATGA", 1, &options).unwrap(), "M..");
+        // TGA is W in the vertebrate mitochondrial code.
+        assert_eq!(translate("ATGTAATGA", 2, &options).unwrap(), "M.W");
+        assert!(matches!(
+            translate("ATG", 99, &options),
+            Err(Pn2CodonError::InvalidTable { requested: 99 })
+        ));
+    }
+
+    #[test]
+    fn reverse_complement_handles_iupac_codes_and_case() {
+        assert_eq!(reverse_complement("ATGCRYKMBVDHN"), "NDHBVKMRYGCAT");
+        assert_eq!(reverse_complement("acgun-"), "-nacgt");
+    }
+}